@@ -0,0 +1,36 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+
+/// Record the git commit and enabled features, for `--version --json`.
+fn main() {
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=NORDIGEN_CLI_COMMIT={}", commit);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(var, _)| {
+            let name = var.strip_prefix("CARGO_FEATURE_")?;
+            Some(name.to_lowercase().replace('_', "-"))
+        })
+        .filter(|name| name != "default")
+        .collect();
+    features.sort();
+    println!(
+        "cargo:rustc-env=NORDIGEN_CLI_FEATURES={}",
+        features.join(",")
+    );
+}
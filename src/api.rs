@@ -0,0 +1,281 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::error::NordigenError;
+use crate::trace;
+
+const API_URL: &str = "https://ob.nordigen.com/api/v2";
+
+/// Where requests go instead of `API_URL`, if set.
+static BASE_URL: Mutex<Option<String>> = Mutex::new(None);
+
+/// Send requests to `url` rather than Nordigen, e.g. to a mock server.
+pub fn set_base_url(url: &str) {
+    *BASE_URL.lock().unwrap() = Some(url.trim_end_matches('/').to_string());
+}
+
+fn endpoint(path: &str) -> String {
+    let base = BASE_URL.lock().unwrap();
+    let base = base.as_deref().unwrap_or(API_URL);
+    format!("{}/{}", base, path.trim_start_matches('/'))
+}
+
+/// Rate limits resetting within this many seconds are waited out, and the
+/// request retried; longer ones are reported.
+const MAX_RATE_LIMIT_WAIT: u64 = 60;
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+fn header_secs(value: &reqwest::header::HeaderValue) -> Option<u64> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    // Retry-After may also be an HTTP date
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs =
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(secs.max(0) as u64)
+}
+
+/// Seconds until the rate limit resets, going by `Retry-After` and
+/// Nordigen's `HTTP_X_RATELIMIT_*_RESET` headers, whichever is latest.
+fn rate_limit_reset(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str().replace('-', "_");
+            name == "retry_after"
+                || (name.contains("ratelimit") && name.ends_with("_reset"))
+        })
+        .filter_map(|(_, value)| header_secs(value))
+        .max()
+}
+
+/// What is left of an account's daily quota for one kind of request, as
+/// last reported by Nordigen's `HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_*`
+/// headers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Quota {
+    pub remaining: u32,
+    pub resets: DateTime<Utc>,
+}
+
+impl Quota {
+    /// Whether the quota still holds, not having reset since.
+    pub fn is_current(&self) -> bool {
+        self.resets > Utc::now()
+    }
+}
+
+/// Quotas seen so far, keyed by account id and kind of request, e.g.
+/// `<id>/transactions`.
+static QUOTAS: Mutex<BTreeMap<String, Quota>> = Mutex::new(BTreeMap::new());
+
+fn header_u32(
+    headers: &reqwest::header::HeaderMap,
+    suffix: &str,
+) -> Option<u32> {
+    headers
+        .iter()
+        .find(|(name, _)| name.as_str().replace('-', "_").ends_with(suffix))
+        .and_then(|(_, value)| value.to_str().ok()?.trim().parse().ok())
+}
+
+/// Keep the account quota reported in response to a request for `path`,
+/// if it is one of `accounts/<id>/<kind>/`.
+fn track_quota(path: &str, headers: &reqwest::header::HeaderMap) {
+    let path = path.split('?').next().unwrap_or_default();
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let key = match parts[..] {
+        ["accounts", id, kind] => format!("{}/{}", id, kind),
+        _ => return,
+    };
+    let remaining = header_u32(headers, "ratelimit_account_success_remaining");
+    let reset = header_u32(headers, "ratelimit_account_success_reset");
+    if let (Some(remaining), Some(reset)) = (remaining, reset) {
+        let resets = Utc::now() + chrono::Duration::seconds(reset.into());
+        tracing::debug!(
+            "{} requests left for {} until {}",
+            remaining,
+            key,
+            resets
+        );
+        let quota = Quota { remaining, resets };
+        QUOTAS.lock().unwrap().insert(key, quota);
+    }
+}
+
+/// The quota left for `kind` requests on an account, if known.
+pub fn quota(account_id: &str, kind: &str) -> Option<Quota> {
+    let key = format!("{}/{}", account_id, kind);
+    QUOTAS
+        .lock()
+        .unwrap()
+        .get(&key)
+        .copied()
+        .filter(Quota::is_current)
+}
+
+/// Quotas seen so far, still current, to be kept across runs.
+pub fn quotas() -> BTreeMap<String, Quota> {
+    let quotas = QUOTAS.lock().unwrap();
+    quotas
+        .iter()
+        .filter(|(_, quota)| quota.is_current())
+        .map(|(key, quota)| (key.clone(), *quota))
+        .collect()
+}
+
+/// Start from quotas seen in earlier runs; those seen since are kept.
+pub fn add_quotas(known: BTreeMap<String, Quota>) {
+    let mut quotas = QUOTAS.lock().unwrap();
+    for (key, quota) in known {
+        quotas.entry(key).or_insert(quota);
+    }
+}
+
+/// Send a request, tracing it if enabled, and parse the JSON response.
+/// When rate limited, waits for the limit to reset if that is soon.
+async fn request<T: DeserializeOwned>(
+    token: &str,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<T, NordigenError> {
+    let url = endpoint(path);
+    let body = body.map(|value| value.to_string());
+    let client = reqwest::Client::new();
+    let mut retries = 0;
+
+    loop {
+        trace::request(method.as_str(), &url, body.as_deref());
+        let mut req = client
+            .request(method.clone(), &url)
+            .bearer_auth(token)
+            .header("accept", "application/json");
+        if let Some(body) = &body {
+            req = req
+                .header("content-type", "application/json")
+                .body(body.clone());
+        }
+
+        let res = match req.send().await {
+            Err(err) => {
+                trace::failure(&err.to_string());
+                return Err(NordigenError::Http(err));
+            }
+            Ok(res) => res,
+        };
+
+        let status = res.status();
+        let headers = res.headers().clone();
+        let text = match res.text().await {
+            Err(err) => {
+                trace::failure(&err.to_string());
+                return Err(NordigenError::Http(err));
+            }
+            Ok(text) => text,
+        };
+        trace::response(status.as_u16(), &headers, &text);
+        track_quota(path, &headers);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let reset = rate_limit_reset(&headers);
+            match reset {
+                Some(secs)
+                    if secs <= MAX_RATE_LIMIT_WAIT
+                        && retries < MAX_RATE_LIMIT_RETRIES =>
+                {
+                    tracing::warn!(
+                        "rate limited on {}, retry {} in {}s",
+                        path,
+                        retries + 1,
+                        secs
+                    );
+                    tokio::time::sleep(std::time::Duration::from_secs(secs))
+                        .await;
+                    retries += 1;
+                    continue;
+                }
+                _ => {
+                    tracing::warn!("rate limited on {}, giving up", path);
+                    return Err(NordigenError::rate_limited(&text, reset));
+                }
+            }
+        }
+        if !status.is_success() {
+            return Err(NordigenError::from_response(status.as_u16(), &text));
+        }
+        return match serde_json::from_str::<T>(&text) {
+            Err(err) => Err(NordigenError::Parse(err.to_string())),
+            Ok(value) => Ok(value),
+        };
+    }
+}
+
+/// Perform an authenticated GET request against the Nordigen API, for
+/// endpoints not covered by the `nordigen` crate.
+pub async fn get<T: DeserializeOwned>(
+    token: &str,
+    path: &str,
+) -> Result<T, NordigenError> {
+    request(token, reqwest::Method::GET, path, None).await
+}
+
+const PAGE_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+struct Page<T> {
+    next: Option<String>,
+    results: Vec<T>,
+}
+
+/// GET every entry of a paginated listing, such as `requisitions/`.
+pub async fn get_all<T: DeserializeOwned>(
+    token: &str,
+    path: &str,
+) -> Result<Vec<T>, NordigenError> {
+    let mut res: Vec<T> = vec![];
+    loop {
+        let page_path =
+            format!("{}?limit={}&offset={}", path, PAGE_LIMIT, res.len());
+        let page: Page<T> = get(token, &page_path).await?;
+        let last = page.next.is_none() || page.results.is_empty();
+        res.extend(page.results);
+        if last {
+            break;
+        }
+    }
+    Ok(res)
+}
+
+/// Perform an authenticated POST request with a JSON body.
+pub async fn post<B: Serialize, T: DeserializeOwned>(
+    token: &str,
+    path: &str,
+    body: &B,
+) -> Result<T, NordigenError> {
+    let body = match serde_json::to_value(body) {
+        Err(err) => return Err(NordigenError::Parse(err.to_string())),
+        Ok(value) => value,
+    };
+    request(token, reqwest::Method::POST, path, Some(body)).await
+}
+
+/// Perform an authenticated DELETE request, discarding the response body.
+pub async fn delete(token: &str, path: &str) -> Result<(), NordigenError> {
+    let _: serde_json::Value =
+        request(token, reqwest::Method::DELETE, path, None).await?;
+    Ok(())
+}
@@ -0,0 +1,21 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+pub mod accounts;
+pub mod agreements;
+pub mod auth;
+pub mod callback;
+pub mod institutions;
+pub mod requisitions;
+
+pub use accounts::*;
+pub use agreements::*;
+pub use auth::*;
+pub use callback::*;
+pub use institutions::*;
+pub use requisitions::*;
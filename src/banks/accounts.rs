@@ -0,0 +1,490 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::{self, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::requisitions::requisition;
+use crate::api;
+use crate::codes::CurrencyCode;
+use crate::error::NordigenError;
+use crate::money;
+use crate::msg::Progress;
+
+/// Accounts fetched at once when listing several; each one takes two
+/// requests, and going any wider mostly runs into the rate limits.
+pub const MAX_CONCURRENT_FETCHES: usize = 4;
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum UnstructuredAddress {
+    Line(String),
+    Lines(Vec<String>),
+}
+
+impl std::fmt::Display for UnstructuredAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnstructuredAddress::Line(line) => write!(f, "{}", line),
+            UnstructuredAddress::Lines(lines) => {
+                write!(f, "{}", lines.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredAddress {
+    pub street_name: Option<String>,
+    pub building_number: Option<String>,
+    pub town_name: Option<String>,
+    pub post_code: Option<String>,
+    pub country: Option<String>,
+}
+
+impl std::fmt::Display for StructuredAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<&str> = [
+            &self.street_name,
+            &self.building_number,
+            &self.post_code,
+            &self.town_name,
+            &self.country,
+        ]
+        .iter()
+        .filter_map(|v| v.as_deref())
+        .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Account details, as returned by `/accounts/{id}/details/`.
+///
+/// Banks are free to omit most of these fields. For jointly owned accounts
+/// `owner_name` may hold several names, as provided by the bank.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetails {
+    pub resource_id: Option<String>,
+    pub iban: Option<String>,
+    pub bban: Option<String>,
+    pub bic: Option<String>,
+    pub msisdn: Option<String>,
+    pub currency: Option<String>,
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub product: Option<String>,
+    pub masked_pan: Option<String>,
+    pub cash_account_type: Option<String>,
+    pub status: Option<String>,
+    pub usage: Option<String>,
+    /// Free-form characteristics of the account, e.g. a card's
+    pub details: Option<String>,
+    /// Resource id of the account a card account is linked to
+    pub linked_accounts: Option<String>,
+    pub owner_name: Option<String>,
+    pub owner_address_unstructured: Option<UnstructuredAddress>,
+    pub owner_address_structured: Option<StructuredAddress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct Amount {
+    pub amount: String,
+    pub currency: String,
+}
+
+/// An account balance, as returned by `/accounts/{id}/balances/`.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Balance {
+    pub balance_amount: Amount,
+    pub balance_type: String,
+    pub reference_date: Option<NaiveDate>,
+    pub last_change_date_time: Option<DateTime<Utc>>,
+    pub credit_limit_included: Option<bool>,
+}
+
+/// Pick the first balance matching `types`, in order of preference, falling
+/// back to whichever balance the bank reported first.
+pub fn preferred_balance<'a>(
+    balances: &'a [Balance],
+    types: &[String],
+) -> Option<&'a Balance> {
+    types
+        .iter()
+        .find_map(|t| balances.iter().find(|b| &b.balance_type == t))
+        .or_else(|| balances.first())
+}
+
+#[derive(Deserialize)]
+struct BalancesResponse {
+    balances: Vec<Balance>,
+}
+
+pub async fn balances(
+    token: &str,
+    account_id: &str,
+) -> Result<Vec<Balance>, NordigenError> {
+    let path = format!("accounts/{}/balances/", account_id);
+    let res: BalancesResponse = api::get(token, &path).await?;
+    Ok(res.balances)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountReference {
+    pub iban: Option<String>,
+    pub bban: Option<String>,
+    pub masked_pan: Option<String>,
+}
+
+/// A transaction, as returned by `/accounts/{id}/transactions/`.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub transaction_id: Option<String>,
+    pub internal_transaction_id: Option<String>,
+    pub entry_reference: Option<String>,
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    pub transaction_amount: Amount,
+    pub creditor_name: Option<String>,
+    pub creditor_account: Option<AccountReference>,
+    pub debtor_name: Option<String>,
+    pub debtor_account: Option<AccountReference>,
+    pub remittance_information_unstructured: Option<String>,
+    #[serde(default)]
+    pub remittance_information_unstructured_array: Vec<String>,
+    pub bank_transaction_code: Option<String>,
+    pub proprietary_bank_transaction_code: Option<String>,
+    pub creditor_id: Option<String>,
+    pub mandate_id: Option<String>,
+    pub end_to_end_id: Option<String>,
+}
+
+/// SEPA direct debit identifiers of a transaction.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SepaInfo {
+    pub creditor_id: Option<String>,
+    pub mandate_reference: Option<String>,
+    pub end_to_end_id: Option<String>,
+}
+
+/// Tags SEPA remittance information is made of; some banks run them
+/// together without spaces in between.
+const SEPA_TAGS: &[&str] = &[
+    "EREF", "KREF", "MREF", "CRED", "DEBT", "SVWZ", "ABWA", "ABWE",
+];
+
+/// Find the value following a SEPA tag (e.g. `MREF+` or `MREF:`) in
+/// remittance information, as some banks only report them there.
+fn sepa_tag(info: &str, tag: &str) -> Option<String> {
+    let mut rest = info;
+    while let Some(pos) = rest.find(tag) {
+        let after = &rest[pos + tag.len()..];
+        if let Some(value) = after.strip_prefix(|c: char| c == '+' || c == ':')
+        {
+            let value = value.trim_start();
+            let next_tag = SEPA_TAGS
+                .iter()
+                .filter_map(|tag| value.find(&format!("{}+", tag)));
+            let end = value
+                .find(char::is_whitespace)
+                .into_iter()
+                .chain(next_tag)
+                .min()
+                .unwrap_or(value.len());
+            if end > 0 {
+                return Some(value[..end].to_string());
+            }
+        }
+        rest = after;
+    }
+    None
+}
+
+impl Transaction {
+    pub fn amount(&self) -> Option<money::Amount> {
+        self.transaction_amount.amount.parse().ok()
+    }
+
+    pub fn is_debit(&self) -> bool {
+        self.transaction_amount.amount.trim_start().starts_with('-')
+    }
+
+    /// The date the transaction took effect, falling back to its booking
+    /// date for banks not reporting value dates.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.value_date.or(self.booking_date)
+    }
+
+    /// The other party's name and IBAN: the creditor for outgoing
+    /// transactions, the debtor for incoming ones.
+    pub fn counterparty(&self) -> (Option<&str>, Option<&str>) {
+        let (name, account) = if self.is_debit() {
+            (&self.creditor_name, &self.creditor_account)
+        } else {
+            (&self.debtor_name, &self.debtor_account)
+        };
+        let iban = account.as_ref().and_then(|a| a.iban.as_deref());
+        (name.as_deref(), iban)
+    }
+
+    pub fn sepa(&self) -> SepaInfo {
+        let info = self.info().unwrap_or_default();
+        SepaInfo {
+            creditor_id: self
+                .creditor_id
+                .clone()
+                .or_else(|| sepa_tag(&info, "CRED")),
+            mandate_reference: self
+                .mandate_id
+                .clone()
+                .or_else(|| sepa_tag(&info, "MREF")),
+            end_to_end_id: self
+                .end_to_end_id
+                .clone()
+                .or_else(|| sepa_tag(&info, "EREF")),
+        }
+    }
+
+    pub fn info(&self) -> Option<String> {
+        if let Some(info) = &self.remittance_information_unstructured {
+            return Some(info.clone());
+        } else if !self.remittance_information_unstructured_array.is_empty() {
+            return Some(
+                self.remittance_information_unstructured_array.join(" "),
+            );
+        }
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct Transactions {
+    #[serde(default)]
+    pub booked: Vec<Transaction>,
+    #[serde(default)]
+    pub pending: Vec<Transaction>,
+}
+
+#[derive(Deserialize)]
+struct TransactionsResponse {
+    transactions: Transactions,
+}
+
+/// Transactions of an account, optionally limited to those booked between
+/// `from` and `to`, inclusive.
+pub async fn transactions(
+    token: &str,
+    account_id: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Transactions, NordigenError> {
+    let mut params: Vec<String> = vec![];
+    if let Some(date) = from {
+        params.push(format!("date_from={}", date));
+    }
+    if let Some(date) = to {
+        params.push(format!("date_to={}", date));
+    }
+    let mut path = format!("accounts/{}/transactions/", account_id);
+    if !params.is_empty() {
+        path = format!("{}?{}", path, params.join("&"));
+    }
+    let res: TransactionsResponse = api::get(token, &path).await?;
+    Ok(res.transactions)
+}
+
+#[derive(Deserialize)]
+struct AccountDetailsResponse {
+    account: AccountDetails,
+}
+
+pub async fn account_details(
+    token: &str,
+    account_id: &str,
+) -> Result<AccountDetails, NordigenError> {
+    let path = format!("accounts/{}/details/", account_id);
+    let res: AccountDetailsResponse = api::get(token, &path).await?;
+    Ok(res.account)
+}
+
+/// Account metadata, as returned by `/accounts/{id}/`.
+///
+/// Card accounts usually have no IBAN, being identified by their masked PAN
+/// in the account details instead.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct AccountMeta {
+    pub id: String,
+    pub iban: Option<String>,
+    pub institution_id: String,
+    pub status: Option<String>,
+    pub owner_name: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub last_accessed: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct Account {
+    pub meta: AccountMeta,
+    pub details: AccountDetails,
+}
+
+impl Account {
+    /// Human readable identifier for the account: its IBAN if it has one,
+    /// its masked PAN otherwise.
+    pub fn identifier(&self) -> String {
+        if let Some(iban) = &self.meta.iban {
+            return iban.clone();
+        } else if let Some(pan) = &self.details.masked_pan {
+            return pan.clone();
+        }
+        self.meta.id.clone()
+    }
+
+    pub fn has_iban(&self, iban: &str) -> bool {
+        match &self.meta.iban {
+            None => false,
+            Some(value) => value == iban,
+        }
+    }
+
+    pub fn has_currency(&self, currency: &CurrencyCode) -> bool {
+        match &self.details.currency {
+            None => false,
+            Some(value) => value.eq_ignore_ascii_case(&currency.to_string()),
+        }
+    }
+
+    /// Whether `name` is this account's name, display name or product,
+    /// ignoring case.
+    pub fn has_name(&self, name: &str) -> bool {
+        [
+            &self.details.name,
+            &self.details.display_name,
+            &self.details.product,
+        ]
+        .iter()
+        .filter_map(|v| v.as_deref())
+        .any(|v| v.trim().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Whether this account's masked PAN ends with `suffix`.
+    pub fn has_pan_suffix(&self, suffix: &str) -> bool {
+        match &self.details.masked_pan {
+            None => false,
+            Some(pan) => !suffix.is_empty() && pan.ends_with(suffix),
+        }
+    }
+}
+
+pub async fn account_meta(
+    token: &str,
+    account_id: &str,
+) -> Result<AccountMeta, NordigenError> {
+    let path = format!("accounts/{}/", account_id);
+    api::get(token, &path).await
+}
+
+pub async fn account(
+    token: &str,
+    account_id: &str,
+) -> Result<Account, NordigenError> {
+    let meta = account_meta(token, account_id).await?;
+    let details = account_details(token, account_id).await?;
+    Ok(Account { meta, details })
+}
+
+/// Obtain metadata and details for all accounts linked to a requisition.
+pub async fn accounts(
+    token: &str,
+    requisition_id: &str,
+) -> Result<Vec<Account>, NordigenError> {
+    let requisition = requisition(token, requisition_id).await?;
+    accounts_by_id(token, &requisition.accounts).await
+}
+
+/// Obtain metadata and details for the given accounts, in the same order,
+/// with at most `MAX_CONCURRENT_FETCHES` accounts being fetched at a time.
+pub async fn accounts_by_id(
+    token: &str,
+    account_ids: &[String],
+) -> Result<Vec<Account>, NordigenError> {
+    let progress = Progress::start("accounts");
+    let total = account_ids.len();
+    let mut fetches = stream::iter(account_ids)
+        .map(|account_id| account(token, account_id))
+        .buffered(MAX_CONCURRENT_FETCHES);
+
+    let mut res: Vec<Account> = vec![];
+    while let Some(result) = fetches.next().await {
+        match result {
+            Err(err) => {
+                progress.finish(false);
+                return Err(err);
+            }
+            Ok(val) => res.push(val),
+        };
+        progress.advance(res.len(), total);
+    }
+    progress.finish(true);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sepa_tag;
+
+    const DIRECT_DEBIT: &str = "EREF+NOTPROVIDED MREF+MD-2023-001 \
+                                CRED+DE98ZZZ09999999999 SVWZ+Strom Januar";
+
+    #[test]
+    fn finds_each_tag() {
+        let tag = |name| sepa_tag(DIRECT_DEBIT, name);
+        assert_eq!(tag("EREF").as_deref(), Some("NOTPROVIDED"));
+        assert_eq!(tag("MREF").as_deref(), Some("MD-2023-001"));
+        assert_eq!(tag("CRED").as_deref(), Some("DE98ZZZ09999999999"));
+    }
+
+    #[test]
+    fn tag_at_the_end() {
+        assert_eq!(
+            sepa_tag("SVWZ+Beitrag 2023 MREF+M-77", "MREF").as_deref(),
+            Some("M-77")
+        );
+        assert_eq!(
+            sepa_tag("Beitrag 2023 MREF: M-78", "MREF").as_deref(),
+            Some("M-78")
+        );
+    }
+
+    #[test]
+    fn missing_tag() {
+        assert_eq!(sepa_tag("Lastschrift Strom Januar", "MREF"), None);
+        assert_eq!(sepa_tag("MREFERENCE 12", "MREF"), None);
+        assert_eq!(sepa_tag("Beitrag MREF+", "MREF"), None);
+        assert_eq!(sepa_tag("", "CRED"), None);
+    }
+
+    #[test]
+    fn adjacent_tags() {
+        let info = "EREF+E-1MREF+M-2CRED+DE98ZZZ09999999999SVWZ+Miete";
+        assert_eq!(sepa_tag(info, "EREF").as_deref(), Some("E-1"));
+        assert_eq!(sepa_tag(info, "MREF").as_deref(), Some("M-2"));
+        assert_eq!(
+            sepa_tag(info, "CRED").as_deref(),
+            Some("DE98ZZZ09999999999")
+        );
+        // an empty value isn't the next tag's
+        assert_eq!(sepa_tag("MREF+ CRED+DE98ZZZ09999999999", "MREF"), None);
+    }
+}
@@ -0,0 +1,76 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api;
+use crate::error::NordigenError;
+
+/// An end user agreement, as returned by `/agreements/enduser/{id}/`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Agreement {
+    pub id: String,
+    pub created: Option<DateTime<Utc>>,
+    pub institution_id: String,
+    pub max_historical_days: u32,
+    pub access_valid_for_days: u32,
+    #[serde(default)]
+    pub access_scope: Vec<String>,
+    pub accepted: Option<DateTime<Utc>>,
+}
+
+impl Agreement {
+    /// When access runs out, counting from its acceptance; `None` if the
+    /// end user has yet to accept it.
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        let days = chrono::Duration::days(self.access_valid_for_days as i64);
+        self.accepted.map(|accepted| accepted + days)
+    }
+}
+
+/// Body of a `POST /agreements/enduser/` request. Terms left unset take
+/// Nordigen's defaults: 90 days of history, access valid for 90 days, and
+/// every scope.
+#[derive(Serialize, Debug)]
+pub struct NewAgreement {
+    pub institution_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_historical_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_valid_for_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_scope: Option<Vec<String>>,
+}
+
+pub async fn create_agreement(
+    token: &str,
+    new: &NewAgreement,
+) -> Result<Agreement, NordigenError> {
+    api::post(token, "agreements/enduser/", new).await
+}
+
+pub async fn agreement(
+    token: &str,
+    agreement_id: &str,
+) -> Result<Agreement, NordigenError> {
+    let path = format!("agreements/enduser/{}/", agreement_id);
+    api::get(token, &path).await
+}
+
+pub async fn agreements(token: &str) -> Result<Vec<Agreement>, NordigenError> {
+    api::get_all(token, "agreements/enduser/").await
+}
+
+pub async fn delete_agreement(
+    token: &str,
+    agreement_id: &str,
+) -> Result<(), NordigenError> {
+    let path = format!("agreements/enduser/{}/", agreement_id);
+    api::delete(token, &path).await
+}
@@ -0,0 +1,239 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use super::accounts::Account;
+use super::agreements;
+use super::callback::{CallbackListener, Outcome};
+use super::institutions;
+use super::requisitions::{self, NewRequisition, Requisition};
+use crate::error::NordigenError;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RequisitionRef {
+    pub requisition_id: String,
+}
+
+/// What `bank authorize` keeps on disk for later account access. Shares
+/// its layout with files written by earlier versions, which lack the
+/// metadata kept alongside the requisition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BankAuthState {
+    #[serde(default)]
+    pub bank_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub institution_name: Option<String>,
+    pub requisition: RequisitionRef,
+    /// When the requisition was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agreement_id: Option<String>,
+    /// Where the end user was sent back to after the bank's authorization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<String>,
+    /// When access runs out, as per the agreement once accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    /// The requisition's accounts, kept once fetched so that commands
+    /// selecting one needn't fetch them all again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<Account>,
+}
+
+impl BankAuthState {
+    pub fn new(bank_id: &str, requisition: &Requisition) -> BankAuthState {
+        BankAuthState {
+            bank_id: bank_id.to_string(),
+            institution_name: None,
+            requisition: RequisitionRef {
+                requisition_id: requisition.id.clone(),
+            },
+            created: requisition.created,
+            agreement_id: requisition.agreement.clone(),
+            redirect: requisition.redirect.clone(),
+            expires: None,
+            accounts: vec![],
+        }
+    }
+}
+
+/// How often to check on a requisition, when there's no callback to wait
+/// for.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long to keep polling a requisition before giving up on the end
+/// user, unless set otherwise with `AuthFlow::with_timeout`.
+pub const DEFAULT_POLL_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(15 * 60);
+
+/// A requisition created with Nordigen, waiting for the end user to go
+/// through the bank's authorization.
+pub struct AuthFlow {
+    pub bank_id: String,
+    pub requisition: Requisition,
+    /// Resolved once the end user is redirected back; without it, the
+    /// requisition is polled until it's no longer pending.
+    callback: Option<oneshot::Receiver<Outcome>>,
+    /// How long to poll for, without a callback.
+    timeout: std::time::Duration,
+}
+
+fn new_reference() -> String {
+    format!(
+        "nordigen-cli-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos()
+    )
+}
+
+impl AuthFlow {
+    async fn create(
+        token: &str,
+        bank_id: &str,
+        agreement: Option<String>,
+        redirect: String,
+        reference: String,
+        callback: Option<oneshot::Receiver<Outcome>>,
+    ) -> Result<AuthFlow, NordigenError> {
+        let new = NewRequisition {
+            redirect,
+            institution_id: bank_id.to_string(),
+            reference,
+            agreement,
+            user_language: None,
+        };
+        let requisition = requisitions::create_requisition(token, &new).await?;
+
+        Ok(AuthFlow {
+            bank_id: bank_id.to_string(),
+            requisition,
+            callback,
+            timeout: DEFAULT_POLL_TIMEOUT,
+        })
+    }
+
+    /// Give up polling the requisition after `timeout`, rather than
+    /// `DEFAULT_POLL_TIMEOUT`; flows waiting for a callback aren't
+    /// affected, as waiting spends no requests.
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> AuthFlow {
+        self.timeout = timeout;
+        self
+    }
+
+    pub async fn start(
+        token: &str,
+        bank_id: &str,
+        agreement: Option<String>,
+        listener: &CallbackListener,
+    ) -> Result<AuthFlow, NordigenError> {
+        let reference = new_reference();
+        let callback = listener.expect(&reference);
+        AuthFlow::create(
+            token,
+            bank_id,
+            agreement,
+            listener.redirect_url(),
+            reference,
+            Some(callback),
+        )
+        .await
+    }
+
+    /// Start a flow that doesn't listen for the redirect, for when the
+    /// browser can't reach this host; the end user lands on `redirect`,
+    /// which need not be served by anyone.
+    pub async fn start_headless(
+        token: &str,
+        bank_id: &str,
+        agreement: Option<String>,
+        redirect: &str,
+    ) -> Result<AuthFlow, NordigenError> {
+        AuthFlow::create(
+            token,
+            bank_id,
+            agreement,
+            redirect.to_string(),
+            new_reference(),
+            None,
+        )
+        .await
+    }
+
+    /// Wait until the end user is done with the bank, then check the
+    /// requisition was indeed linked.
+    pub async fn finish(
+        self,
+        token: &str,
+    ) -> Result<BankAuthState, NordigenError> {
+        let requisition = match self.callback {
+            Some(callback) => {
+                match callback.await {
+                    Err(_) => {
+                        return Err(NordigenError::Authorization(
+                            String::from("Callback listener went away"),
+                        ));
+                    }
+                    Ok(Err(err)) => {
+                        return Err(NordigenError::Authorization(format!(
+                            "Bank authorization failed: {}",
+                            err
+                        )));
+                    }
+                    Ok(Ok(_)) => {}
+                };
+                requisitions::requisition(token, &self.requisition.id).await?
+            }
+            None => {
+                let deadline = tokio::time::Instant::now() + self.timeout;
+                loop {
+                    let requisition =
+                        requisitions::requisition(token, &self.requisition.id)
+                            .await?;
+                    if !requisition.is_pending() {
+                        break requisition;
+                    }
+                    if tokio::time::Instant::now() + POLL_INTERVAL > deadline {
+                        return Err(NordigenError::Authorization(format!(
+                            "Timed out after {}s waiting for requisition {} \
+                             to be linked",
+                            self.timeout.as_secs(),
+                            self.requisition.id
+                        )));
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        };
+
+        if requisition.status != "LN" {
+            return Err(NordigenError::Authorization(format!(
+                "Requisition {} not linked; status is {}",
+                requisition.id, requisition.status
+            )));
+        }
+
+        // Neither is needed to access the accounts, so any error is left
+        // for whoever uses them to run into.
+        let mut state = BankAuthState::new(&self.bank_id, &requisition);
+        if let Ok(details) =
+            institutions::institution(token, &self.bank_id).await
+        {
+            state.institution_name = Some(details.institution.name);
+        }
+        if let Some(id) = &requisition.agreement {
+            if let Ok(agreement) = agreements::agreement(token, id).await {
+                state.expires = agreement.expires();
+            }
+        }
+        Ok(state)
+    }
+}
@@ -0,0 +1,170 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+use crate::error::NordigenError;
+
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 1337;
+
+pub type Outcome = Result<(), String>;
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<Outcome>>>>;
+
+/// A single HTTP listener for the end user's redirect after authorizing
+/// with a bank. Nordigen appends `?ref=<reference>` to the redirect URL,
+/// which is used to wake whichever flow registered that reference, so any
+/// number of authorizations may be pending at once.
+pub struct CallbackListener {
+    host: String,
+    addr: SocketAddr,
+    pending: Pending,
+}
+
+impl CallbackListener {
+    /// Listen on `host` and `port`, which also make up the redirect URL.
+    /// A port of 0 picks any free one.
+    pub async fn bind(
+        host: &str,
+        port: u16,
+    ) -> Result<CallbackListener, NordigenError> {
+        let address = format!("{}:{}", host, port);
+        let listener = match TcpListener::bind(&address).await {
+            Err(err) => {
+                return Err(NordigenError::Io(
+                    format!("Unable to listen for callbacks on {}", address),
+                    err,
+                ));
+            }
+            Ok(res) => res,
+        };
+        let addr = match listener.local_addr() {
+            Err(err) => {
+                return Err(NordigenError::Io(
+                    String::from("Unable to obtain local address"),
+                    err,
+                ));
+            }
+            Ok(res) => res,
+        };
+
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let routes = pending.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, routes.clone()));
+            }
+        });
+
+        Ok(CallbackListener {
+            host: host.to_string(),
+            addr,
+            pending,
+        })
+    }
+
+    /// URL to hand Nordigen as the requisition's redirect.
+    pub fn redirect_url(&self) -> String {
+        format!("http://{}:{}/", self.host, self.addr.port())
+    }
+
+    /// Wait for the callback carrying `reference`.
+    pub fn expect(&self, reference: &str) -> oneshot::Receiver<Outcome> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(reference.to_string(), tx);
+        rx
+    }
+}
+
+/// Obtain the query parameters from a request line such as
+/// `GET /?ref=abc&error=... HTTP/1.1`.
+fn query(request: &str) -> HashMap<String, String> {
+    let target = request.split_whitespace().nth(1).unwrap_or("");
+    let query = match target.split_once('?') {
+        None => return HashMap::new(),
+        Some((_, query)) => query,
+    };
+
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+async fn serve(mut stream: TcpStream, pending: Pending) {
+    let mut buf = [0u8; 4096];
+    let len = match stream.read(&mut buf).await {
+        Err(_) => return,
+        Ok(len) => len,
+    };
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let params = query(request.lines().next().unwrap_or(""));
+
+    let waiter = match params.get("ref") {
+        None => None,
+        Some(reference) => pending.lock().unwrap().remove(reference),
+    };
+
+    let (status, body) = match waiter {
+        None => ("404 Not Found", "Unknown authorization reference.\n"),
+        Some(tx) => {
+            let outcome = match params.get("error") {
+                None => Ok(()),
+                Some(error) => Err(match params.get("details") {
+                    None => error.clone(),
+                    Some(details) => format!("{}: {}", error, details),
+                }),
+            };
+            let body = match &outcome {
+                Ok(_) => "Authorization complete; you may close this window.\n",
+                Err(_) => "Authorization failed; see the terminal.\n",
+            };
+            let _ = tx.send(outcome);
+            ("200 OK", body)
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::query;
+
+    #[test]
+    fn query_decodes_percent_and_plus() {
+        let params = query(
+            "GET /?ref=nordigen%2Dcli&error=UserCancelledSession\
+             &details=User+cancelled%3A+%22no%22 HTTP/1.1",
+        );
+        assert_eq!(params["ref"], "nordigen-cli");
+        assert_eq!(params["error"], "UserCancelledSession");
+        assert_eq!(params["details"], "User cancelled: \"no\"");
+    }
+
+    #[test]
+    fn query_without_parameters() {
+        assert!(query("GET / HTTP/1.1").is_empty());
+        assert!(query("").is_empty());
+    }
+}
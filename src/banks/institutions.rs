@@ -0,0 +1,183 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::api;
+use crate::codes::CountryCode;
+use crate::error::NordigenError;
+use crate::fuzzy;
+
+/// Nordigen's sandbox institution, with test accounts and transactions.
+pub const SANDBOX_ID: &str = "SANDBOXFINANCE_SFIN0000";
+
+/// An institution, as returned by `/institutions/`.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct Institution {
+    pub id: String,
+    pub name: String,
+    pub bic: Option<String>,
+    #[serde(default)]
+    pub transaction_total_days: String,
+    #[serde(default)]
+    pub countries: Vec<String>,
+    pub logo: Option<String>,
+}
+
+impl Institution {
+    /// Days of transaction history available, or 0 if unknown.
+    pub fn total_days(&self) -> u32 {
+        self.transaction_total_days.parse().unwrap_or(0)
+    }
+
+    /// Whether the id or name contain `text`, ignoring case.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.id.to_lowercase().contains(&text)
+            || self.name.to_lowercase().contains(&text)
+    }
+}
+
+/// Institution details, as returned by `/institutions/{id}/`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstitutionDetails {
+    #[serde(flatten)]
+    pub institution: Institution,
+    pub max_access_valid_for_days: Option<String>,
+    /// Supported payment products, keyed by payment type
+    #[serde(default)]
+    pub supported_payments: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub supported_features: Vec<String>,
+    #[serde(default)]
+    pub identification_codes: Vec<String>,
+}
+
+impl InstitutionDetails {
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.supported_features.iter().any(|f| f == feature)
+    }
+
+    /// Account types the institution supports, as derived from its
+    /// `*_accounts` features.
+    pub fn account_types(&self) -> Vec<&str> {
+        self.supported_features
+            .iter()
+            .filter_map(|f| f.strip_suffix("_accounts"))
+            .collect()
+    }
+}
+
+pub async fn institution(
+    token: &str,
+    institution_id: &str,
+) -> Result<InstitutionDetails, NordigenError> {
+    let path = format!("institutions/{}/", institution_id);
+    api::get(token, &path).await
+}
+
+/// List available institutions, optionally restricted to a given country.
+pub async fn list(
+    token: &str,
+    country: &Option<CountryCode>,
+) -> Result<Vec<Institution>, NordigenError> {
+    let path = match country {
+        None => String::from("institutions/"),
+        Some(code) => format!("institutions/?country={}", code),
+    };
+    api::get(token, &path).await
+}
+
+/// Institutions whose id or name most resemble `query`, best first; used
+/// to suggest a bank when an id is mistyped.
+pub fn closest<'a>(
+    institutions: &'a [Institution],
+    query: &str,
+    max: usize,
+) -> Vec<&'a Institution> {
+    let query = query.to_uppercase();
+    let threshold = (query.chars().count() / 3).max(2);
+
+    let mut scored: Vec<(usize, &Institution)> = institutions
+        .iter()
+        .map(|entry| {
+            let id = entry.id.to_uppercase();
+            let name = entry.name.to_uppercase();
+            let dist = if id.contains(&query) || name.contains(&query) {
+                0
+            } else {
+                fuzzy::levenshtein(&id, &query)
+                    .min(fuzzy::levenshtein(&name, &query))
+            };
+            (dist, entry)
+        })
+        .filter(|(dist, _)| *dist <= threshold)
+        .collect();
+
+    scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+    scored
+        .into_iter()
+        .take(max)
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, Institution};
+
+    fn institution(id: &str, name: &str) -> Institution {
+        Institution {
+            id: id.to_string(),
+            name: name.to_string(),
+            bic: None,
+            transaction_total_days: String::from("90"),
+            countries: vec![String::from("PT")],
+            logo: None,
+        }
+    }
+
+    fn ids(found: Vec<&Institution>) -> Vec<&str> {
+        found.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    fn banks() -> Vec<Institution> {
+        vec![
+            institution("CAIXA_GERAL_CGDIPTPL", "Caixa Geral de Depositos"),
+            institution("REVOLUT_REVOGB21", "Revolut"),
+            institution("ACTIVOBANK_ACTVPTPL", "ActivoBank"),
+            institution("BANCOBPI_BBPIPTPL", "Banco BPI"),
+        ]
+    }
+
+    #[test]
+    fn substrings_come_first() {
+        let banks = banks();
+        assert_eq!(ids(closest(&banks, "revolut", 5)), ["REVOLUT_REVOGB21"]);
+        assert_eq!(
+            ids(closest(&banks, "PTPL", 2)),
+            ["ACTIVOBANK_ACTVPTPL", "BANCOBPI_BBPIPTPL"]
+        );
+    }
+
+    #[test]
+    fn suggests_for_typos() {
+        let banks = banks();
+        assert_eq!(ids(closest(&banks, "revolt", 5)), ["REVOLUT_REVOGB21"]);
+        assert_eq!(ids(closest(&banks, "Banco BIP", 5)), ["BANCOBPI_BBPIPTPL"]);
+    }
+
+    #[test]
+    fn nothing_close() {
+        assert!(closest(&banks(), "SANTANDER", 5).is_empty());
+        assert!(closest(&[], "REVOLUT", 5).is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api;
+use crate::error::NordigenError;
+
+/// A requisition, as returned by `/requisitions/{id}/`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Requisition {
+    pub id: String,
+    pub created: Option<DateTime<Utc>>,
+    pub redirect: Option<String>,
+    pub status: String,
+    pub institution_id: String,
+    pub agreement: Option<String>,
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    pub user_language: Option<String>,
+    pub link: String,
+}
+
+impl Requisition {
+    /// Whether the end user has yet to complete the authorization flow.
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status.as_str(), "CR" | "GC" | "UA" | "SA" | "GA")
+    }
+
+    /// Whether account data can be accessed through the requisition.
+    pub fn is_linked(&self) -> bool {
+        self.status == "LN"
+    }
+
+    /// What the status code stands for.
+    pub fn status_description(&self) -> &'static str {
+        match self.status.as_str() {
+            "CR" => "created",
+            "GC" => "giving consent",
+            "UA" => "undergoing authentication",
+            "RJ" => "rejected",
+            "SA" => "selecting accounts",
+            "GA" => "granting access",
+            "LN" => "linked",
+            "SU" => "suspended",
+            "EX" => "expired",
+            _ => "unknown",
+        }
+    }
+}
+
+pub async fn requisition(
+    token: &str,
+    requisition_id: &str,
+) -> Result<Requisition, NordigenError> {
+    let path = format!("requisitions/{}/", requisition_id);
+    api::get(token, &path).await
+}
+
+/// Body of a `POST /requisitions/` request.
+#[derive(Serialize, Debug)]
+pub struct NewRequisition {
+    pub redirect: String,
+    pub institution_id: String,
+    pub reference: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agreement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_language: Option<String>,
+}
+
+pub async fn create_requisition(
+    token: &str,
+    new: &NewRequisition,
+) -> Result<Requisition, NordigenError> {
+    api::post(token, "requisitions/", new).await
+}
+
+/// All requisitions on the account.
+pub async fn requisitions(
+    token: &str,
+) -> Result<Vec<Requisition>, NordigenError> {
+    api::get_all(token, "requisitions/").await
+}
+
+pub async fn delete_requisition(
+    token: &str,
+    requisition_id: &str,
+) -> Result<(), NordigenError> {
+    let path = format!("requisitions/{}/", requisition_id);
+    api::delete(token, &path).await
+}
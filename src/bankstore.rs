@@ -0,0 +1,65 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+
+use nordigen_cli::banks::BankAuthState;
+use serde::{Deserialize, Serialize};
+
+/// Bank authorizations by a user-chosen alias, as kept in banks.json;
+/// an alternative to a `--auth` file per bank.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct BankStore {
+    banks: BTreeMap<String, BankAuthState>,
+}
+
+impl BankStore {
+    fn unknown(&self, alias: &str) -> String {
+        if self.banks.is_empty() {
+            return format!("No bank '{}'; the store is empty.", alias);
+        }
+        let aliases: Vec<&str> =
+            self.banks.keys().map(|k| k.as_str()).collect();
+        format!("No bank '{}'; known are: {}", alias, aliases.join(", "))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BankAuthState)> {
+        self.banks.iter()
+    }
+
+    pub fn get(&self, alias: &str) -> Result<&BankAuthState, String> {
+        self.banks.get(alias).ok_or_else(|| self.unknown(alias))
+    }
+
+    /// Add or replace the authorization under `alias`.
+    pub fn insert(&mut self, alias: &str, state: BankAuthState) {
+        self.banks.insert(alias.to_string(), state);
+    }
+
+    pub fn contains(&self, alias: &str) -> bool {
+        self.banks.contains_key(alias)
+    }
+
+    pub fn remove(&mut self, alias: &str) -> Result<BankAuthState, String> {
+        self.banks.remove(alias).ok_or_else(|| self.unknown(alias))
+    }
+
+    pub fn rename(
+        &mut self,
+        alias: &str,
+        new_alias: &str,
+    ) -> Result<(), String> {
+        if self.banks.contains_key(new_alias) {
+            return Err(format!("Bank '{}' already exists.", new_alias));
+        }
+        let state = self.remove(alias)?;
+        self.banks.insert(new_alias.to_string(), state);
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use nordigen_cli::banks::Institution;
+use nordigen_cli::codes::CountryCode;
+use serde::{Deserialize, Serialize};
+
+/// The institutions of one country, as last fetched.
+#[derive(Serialize, Deserialize)]
+pub struct Listing {
+    pub fetched: DateTime<Utc>,
+    pub institutions: Vec<Institution>,
+}
+
+/// The institutions lists last fetched by `bank list`, per country, as
+/// kept in institutions.json for `bank list --diff`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Catalog {
+    countries: BTreeMap<String, Listing>,
+}
+
+/// Key for `country`, with "all" standing for no country at all.
+fn key(country: &Option<CountryCode>) -> String {
+    match country {
+        None => String::from("all"),
+        Some(code) => code.to_string(),
+    }
+}
+
+impl Catalog {
+    pub fn get(&self, country: &Option<CountryCode>) -> Option<&Listing> {
+        self.countries.get(&key(country))
+    }
+
+    pub fn set(
+        &mut self,
+        country: &Option<CountryCode>,
+        institutions: Vec<Institution>,
+    ) {
+        let listing = Listing {
+            fetched: Utc::now(),
+            institutions,
+        };
+        self.countries.insert(key(country), listing);
+    }
+}
+
+/// How an institution differs between two fetches.
+pub enum Change<'a> {
+    Added(&'a Institution),
+    Removed(&'a Institution),
+    /// The institution as now, and what changed, e.g. "days: 90 -> 730".
+    Changed(&'a Institution, Vec<String>),
+}
+
+impl Change<'_> {
+    pub fn institution(&self) -> &Institution {
+        match self {
+            Change::Added(inst) => inst,
+            Change::Removed(inst) => inst,
+            Change::Changed(inst, _) => inst,
+        }
+    }
+}
+
+fn field_changes(old: &Institution, new: &Institution) -> Vec<String> {
+    let bic = |inst: &Institution| inst.bic.clone().unwrap_or_default();
+    let mut res: Vec<String> = vec![];
+    let mut check = |field: &str, from: String, to: String| {
+        if from != to {
+            res.push(format!("{}: {} -> {}", field, from, to));
+        }
+    };
+    check("name", old.name.clone(), new.name.clone());
+    check("bic", bic(old), bic(new));
+    check(
+        "days",
+        old.transaction_total_days.clone(),
+        new.transaction_total_days.clone(),
+    );
+    check(
+        "countries",
+        old.countries.join(","),
+        new.countries.join(","),
+    );
+    res
+}
+
+/// Institutions added, removed or changed from `old` to `new`, by id.
+pub fn diff<'a>(
+    old: &'a [Institution],
+    new: &'a [Institution],
+) -> Vec<Change<'a>> {
+    let old: BTreeMap<&str, &Institution> =
+        old.iter().map(|inst| (inst.id.as_str(), inst)).collect();
+    let new: BTreeMap<&str, &Institution> =
+        new.iter().map(|inst| (inst.id.as_str(), inst)).collect();
+
+    let mut res: Vec<(&str, Change)> = vec![];
+    for (id, inst) in &new {
+        match old.get(id) {
+            None => res.push((*id, Change::Added(inst))),
+            Some(prev) => {
+                let changes = field_changes(prev, inst);
+                if !changes.is_empty() {
+                    res.push((*id, Change::Changed(inst, changes)));
+                }
+            }
+        }
+    }
+    for (id, inst) in &old {
+        if !new.contains_key(id) {
+            res.push((*id, Change::Removed(inst)));
+        }
+    }
+    res.sort_by(|a, b| a.0.cmp(b.0));
+    res.into_iter().map(|(_, change)| change).collect()
+}
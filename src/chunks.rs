@@ -0,0 +1,166 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::{Datelike, NaiveDate};
+
+/// Period to split exports by, one request and file per period.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Chunk {
+    Monthly,
+    Yearly,
+}
+
+impl std::str::FromStr for Chunk {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monthly" => Ok(Chunk::Monthly),
+            "yearly" => Ok(Chunk::Yearly),
+            _ => Err(format!("unknown chunk '{}'; try monthly or yearly", s)),
+        }
+    }
+}
+
+/// A date window within one period, inclusive on both ends.
+pub struct Window {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Period the window falls in, e.g. `2023-01` or `2023`
+    pub label: String,
+}
+
+/// First day of the period following the one `date` falls in.
+fn next_period(date: NaiveDate, chunk: Chunk) -> Option<NaiveDate> {
+    match chunk {
+        Chunk::Monthly if date.month() == 12 => {
+            NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+        }
+        Chunk::Monthly => {
+            NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+        }
+        Chunk::Yearly => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+    }
+}
+
+/// Split `from` to `to` into windows along period boundaries; the first
+/// and last windows may cover only part of their period.
+pub fn windows(from: NaiveDate, to: NaiveDate, chunk: Chunk) -> Vec<Window> {
+    let mut res = vec![];
+    let mut start = from;
+    while start <= to {
+        let next = next_period(start, chunk);
+        let end = match next.and_then(|d| d.pred_opt()) {
+            Some(end) if end < to => end,
+            _ => to,
+        };
+        let label = match chunk {
+            Chunk::Monthly => start.format("%Y-%m").to_string(),
+            Chunk::Yearly => start.format("%Y").to_string(),
+        };
+        res.push(Window {
+            from: start,
+            to: end,
+            label,
+        });
+        start = match next {
+            None => break,
+            Some(date) => date,
+        };
+    }
+    res
+}
+
+/// `path` with `label` added to its file stem, e.g. `out-2023-01.csv`.
+pub fn chunk_path(path: &std::path::Path, label: &str) -> std::path::PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        None => format!("{}-{}", stem, label),
+        Some(ext) => format!("{}-{}.{}", stem, label, ext.to_string_lossy()),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_path, windows, Chunk};
+    use chrono::NaiveDate;
+    use std::path::{Path, PathBuf};
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    /// Each window as `label from to`.
+    fn spans(from: &str, to: &str, chunk: Chunk) -> Vec<String> {
+        windows(date(from), date(to), chunk)
+            .iter()
+            .map(|w| format!("{} {} {}", w.label, w.from, w.to))
+            .collect()
+    }
+
+    #[test]
+    fn range_within_one_month() {
+        assert_eq!(
+            spans("2023-03-05", "2023-03-20", Chunk::Monthly),
+            ["2023-03 2023-03-05 2023-03-20"]
+        );
+    }
+
+    #[test]
+    fn range_across_the_new_year() {
+        assert_eq!(
+            spans("2022-12-15", "2023-01-10", Chunk::Monthly),
+            [
+                "2022-12 2022-12-15 2022-12-31",
+                "2023-01 2023-01-01 2023-01-10",
+            ]
+        );
+    }
+
+    #[test]
+    fn single_day() {
+        assert_eq!(
+            spans("2023-02-28", "2023-02-28", Chunk::Monthly),
+            ["2023-02 2023-02-28 2023-02-28"]
+        );
+    }
+
+    #[test]
+    fn reversed_range_is_empty() {
+        assert!(spans("2023-02-01", "2023-01-31", Chunk::Monthly).is_empty());
+        assert!(spans("2023-02-01", "2023-01-31", Chunk::Yearly).is_empty());
+    }
+
+    #[test]
+    fn yearly_from_and_to_mid_year() {
+        assert_eq!(
+            spans("2021-06-15", "2023-03-01", Chunk::Yearly),
+            [
+                "2021 2021-06-15 2021-12-31",
+                "2022 2022-01-01 2022-12-31",
+                "2023 2023-01-01 2023-03-01",
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_paths() {
+        assert_eq!(
+            chunk_path(Path::new("out/tx.csv"), "2023-01"),
+            PathBuf::from("out/tx-2023-01.csv")
+        );
+        assert_eq!(
+            chunk_path(Path::new("tx"), "2023"),
+            PathBuf::from("tx-2023")
+        );
+    }
+}
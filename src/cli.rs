@@ -6,50 +6,294 @@
 // the Free Software Foundation, either version 3 of the License, or
 // (at your option) any later version.
 //
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use nordigen_cli::banks;
+use nordigen_cli::codes::{CountryCode, CurrencyCode};
+use nordigen_cli::msg::ProgressFormat;
+
+use crate::chunks::Chunk;
+use crate::examples;
+use crate::i18n::Lang;
+
+/// Formats `bank account transactions --format` takes.
+pub const TRANSACTION_FORMATS: [&str; 5] =
+    ["text", "table", "csv", "ledger", "beancount"];
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(disable_version_flag = true)]
 pub struct Cli {
+    /// Print version
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// With --version, print version, commit, features and formats as JSON
+    #[arg(long, requires = "version")]
+    pub json: bool,
+
+    /// Only print results and errors, without progress bars
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Language for messages (en, pt); defaults to the locale's
+    #[arg(long, global = true, value_name = "LANG")]
+    pub lang: Option<Lang>,
+
+    /// Directory holding config.toml, state.json and bank.json, used
+    /// whenever the corresponding option is not given; defaults to
+    /// ~/.config/nordigen-cli for the config and ~/.local/share/nordigen-cli
+    /// for the rest
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_dir: Option<std::path::PathBuf>,
+
+    /// Use the named profile's config, state and bank files, as created
+    /// with `profile create`, and its own secrets in the keyring
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        conflicts_with = "config_dir"
+    )]
+    pub profile: Option<String>,
+
+    /// Keep tokens in memory only, authorizing from the config's secrets
+    /// on every run; the state file is neither read nor written
+    #[arg(long, global = true)]
+    pub ephemeral: bool,
+
+    /// Refresh the access token when fewer than this many seconds of
+    /// validity remain
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 60)]
+    pub refresh_margin: u64,
+
+    /// Refuse to create or delete requisitions and agreements, or to write
+    /// auth state; refreshed tokens are kept in memory only
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Encrypt state and bank files with the passphrase in FILE, also
+    /// taken from NORDIGEN_PASSPHRASE; plain files are still read
+    #[arg(long, global = true, value_name = "FILE")]
+    pub passphrase_file: Option<std::path::PathBuf>,
+
+    /// Send API requests to URL instead of Nordigen's, e.g. a mock server;
+    /// obtaining and refreshing tokens still goes to Nordigen
+    #[arg(long, global = true, value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// Record HTTP requests and responses to FILE, with tokens, secrets,
+    /// IBANs and names redacted
+    #[arg(long, global = true, value_name = "FILE")]
+    pub trace_http: Option<std::path::PathBuf>,
+
+    /// Report progress of long operations on stderr (json)
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub progress: Option<ProgressFormat>,
+
+    /// Log diagnostics on stderr: -v for info, -vv for debug, including API
+    /// requests and responses with secrets redacted, -vvv for trace
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        conflicts_with = "log_level"
+    )]
+    pub verbose: u8,
+
+    /// Log diagnostics at this level, as with -v
+    #[arg(
+        long,
+        global = true,
+        value_name = "LEVEL",
+        value_parser = ["error", "warn", "info", "debug", "trace"]
+    )]
+    pub log_level: Option<String>,
+
+    /// Log diagnostics to FILE instead of stderr; at debug level, unless
+    /// -v or --log-level say otherwise
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<std::path::PathBuf>,
+
     /// Command to perform
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Create a config file with your secrets, or replace those in the
+    /// keyring, and authorize with them
+    Init(InitCmd),
     /// Authorize application
     Authorize(AuthorizeCmd),
     /// Refresh authorization
     Refresh(RefreshCmd),
+    /// Replace the secrets in the keyring or config file, after checking
+    /// they work
+    RotateSecrets(RotateSecretsCmd),
+    /// Keep secrets in the OS keyring instead of the config file
+    Secrets(SecretsCmd),
     /// Bank related commands
-    Bank(BankCmd),
+    Bank(Box<BankCmd>),
+    /// Keep separate secrets, state and banks per profile
+    Profile(ProfileCmd),
+    /// Counterparty contact book
+    Contacts(ContactsCmd),
+    /// Developer tools for the test suite's fixtures
+    Fixtures(FixturesCmd),
+    /// Print JSON Schemas for the models in JSON output
+    Schema(SchemaCmd),
 }
 
 #[derive(Args)]
-pub struct AuthorizeCmd {
-    /// Config file
+#[command(after_long_help = examples::render("init"))]
+pub struct InitCmd {
+    /// Config file to create
     #[arg(short, long)]
-    pub config: std::path::PathBuf,
+    pub config: Option<std::path::PathBuf>,
 
     /// State file
     #[arg(short, long)]
-    pub state: std::path::PathBuf,
+    pub state: Option<std::path::PathBuf>,
+
+    /// Secret id
+    #[arg(long, value_name = "ID")]
+    pub secret_id: String,
+
+    /// Secret key; read from stdin if not given
+    #[arg(long, value_name = "KEY")]
+    pub secret_key: Option<String>,
+
+    /// Replace an existing config file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct ProfileCmd {
+    #[command(subcommand)]
+    pub command: ProfileCmds,
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCmds {
+    /// List profiles
+    List(ProfileListCmd),
+    /// Create an empty profile, to set up with `--profile NAME init`
+    Create(ProfileCreateCmd),
+    /// Delete a profile, along with its config, state and bank files
+    Delete(ProfileDeleteCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("profile list"))]
+pub struct ProfileListCmd {}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("profile create"))]
+pub struct ProfileCreateCmd {
+    /// Profile name
+    pub name: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("profile delete"))]
+pub struct ProfileDeleteCmd {
+    /// Profile name
+    pub name: String,
+
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
 }
 
 #[derive(Args)]
+pub struct SecretsCmd {
+    #[command(subcommand)]
+    pub command: SecretsCmds,
+}
+
+#[derive(Subcommand)]
+pub enum SecretsCmds {
+    /// Store secrets in the keyring, after checking they work; they are
+    /// used whenever the config file has none
+    Store(SecretsStoreCmd),
+    /// Remove the secrets from the keyring
+    Delete(SecretsDeleteCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("secrets store"))]
+pub struct SecretsStoreCmd {
+    /// Secret id
+    #[arg(long, value_name = "ID")]
+    pub secret_id: String,
+
+    /// Secret key; read from stdin if not given
+    #[arg(long, value_name = "KEY")]
+    pub secret_key: Option<String>,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("secrets delete"))]
+pub struct SecretsDeleteCmd {}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("authorize"))]
+pub struct AuthorizeCmd {
+    /// Config file, or `-` for stdin
+    #[arg(short, long)]
+    pub config: Option<std::path::PathBuf>,
+
+    /// State file, or `-` for stdout
+    #[arg(short, long)]
+    pub state: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("refresh"))]
 pub struct RefreshCmd {
-    /// State file
+    /// State file, or `-` for stdin and stdout
     #[arg(short, long)]
-    pub state: std::path::PathBuf,
+    pub state: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("rotate-secrets"))]
+pub struct RotateSecretsCmd {
+    /// Config file
+    #[arg(short, long)]
+    pub config: Option<std::path::PathBuf>,
+
+    /// State file, replaced by one authorized with the new secrets
+    #[arg(short, long)]
+    pub state: Option<std::path::PathBuf>,
+
+    /// New secret id
+    #[arg(long, value_name = "ID")]
+    pub secret_id: String,
+
+    /// New secret key; read from stdin if not given
+    #[arg(long, value_name = "KEY")]
+    pub secret_key: Option<String>,
 }
 
 #[derive(Args)]
 #[command()]
 pub struct BankCmd {
-    /// State file
-    #[arg(short, long, required = true, value_name = "FILE")]
-    pub state: std::path::PathBuf,
+    /// State file, or `-` for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub state: Option<std::path::PathBuf>,
+
+    /// Config file, for optional settings, or `-` for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Banks store, keeping authorizations by alias, or `-` for stdin
+    #[arg(long, value_name = "FILE")]
+    pub banks: Option<std::path::PathBuf>,
 
     #[command(subcommand)]
     pub command: BankCmds,
@@ -61,33 +305,306 @@ pub enum BankCmds {
     List(BankListCmd),
     /// Authorize a Bank
     Authorize(BankAuthorizeCmd),
+    /// Show a Bank's details
+    Show(BankShowCmd),
+    /// Show what a Bank supports
+    Capabilities(BankCapabilitiesCmd),
+    /// Check whether a Bank authorization is still usable
+    Status(BankStatusCmd),
     /// List Accounts
     Account(BankAccountCmd),
+    /// Requisition related commands
+    Requisition(BankRequisitionCmd),
+    /// End user agreement related commands
+    Agreement(BankAgreementCmd),
+    /// Keep a Bank Authorization file in the banks store, under an alias
+    Add(BankAddCmd),
+    /// Remove a Bank from the banks store
+    Remove(BankRemoveCmd),
+    /// Rename a Bank in the banks store
+    Rename(BankRenameCmd),
 }
 
 #[derive(Args)]
+#[command(after_long_help = examples::render("bank list"))]
 pub struct BankListCmd {
-    /// Country to list
+    /// Country to list (ISO 3166-1 alpha-2)
     #[arg(short, long, value_name = "CODE")]
-    pub country: Option<String>,
+    pub country: Option<CountryCode>,
+
+    /// Only list banks whose name or ID contains this, ignoring case
+    #[arg(long, value_name = "TEXT")]
+    pub search: Option<String>,
+
+    /// Only list banks whose ID starts with this, ignoring case
+    #[arg(long, value_name = "PREFIX")]
+    pub id: Option<String>,
+
+    /// Sort by name, country, or days of transaction history (most first)
+    #[arg(
+        long,
+        value_name = "KEY",
+        value_parser = ["name", "country", "days"]
+    )]
+    pub sort: Option<String>,
+
+    /// Show banks added, removed or changed since the last bank list for
+    /// this country, instead of listing them all
+    #[arg(long, conflicts_with = "sort")]
+    pub diff: bool,
 }
 
 #[derive(Args)]
+#[command(after_long_help = examples::render("bank authorize"))]
 pub struct BankAuthorizeCmd {
+    /// Bank ID
+    #[arg(required_unless_present_any = ["batch", "sandbox"])]
+    pub bank_id: Option<String>,
+
+    /// Authorize Nordigen's sandbox bank, SANDBOXFINANCE_SFIN0000, whose
+    /// test accounts need no real bank credentials
+    #[arg(long, conflicts_with_all = ["bank_id", "batch"])]
+    pub sandbox: bool,
+
+    /// Bank Authorization file, or `-` for stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub auth: Option<std::path::PathBuf>,
+
+    /// Keep the authorization in the banks store, under this alias
+    #[arg(long, value_name = "ALIAS", conflicts_with = "auth")]
+    pub bank: Option<String>,
+
+    /// Authorize every bank listed in a TOML file, all at once
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["bank_id", "auth", "bank"]
+    )]
+    pub batch: Option<std::path::PathBuf>,
+
+    #[command(flatten)]
+    pub terms: AgreementTermsArgs,
+
+    /// Host to listen on for the bank's redirect, and to redirect to
+    #[arg(long, value_name = "HOST", default_value = banks::DEFAULT_HOST)]
+    pub redirect_host: String,
+
+    /// Port to listen on for the bank's redirect; 0 picks a free one
+    #[arg(long, value_name = "PORT", default_value_t = banks::DEFAULT_PORT)]
+    pub redirect_port: u16,
+
+    /// Don't listen for the redirect; instead poll each requisition until
+    /// the bank's authorization is done, for when the browser can't reach
+    /// this host
+    #[arg(long)]
+    pub no_callback: bool,
+
+    /// With --no-callback, give up on a bank not authorized within this
+    /// many minutes
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        default_value_t = 15,
+        requires = "no_callback"
+    )]
+    pub timeout: u64,
+}
+
+/// Terms of an end user agreement; any given on `bank authorize` creates
+/// an agreement for the requisition, instead of using Nordigen's defaults.
+#[derive(Args)]
+pub struct AgreementTermsArgs {
+    /// Days of transaction history to request (Nordigen's default is 90)
+    #[arg(long, value_name = "DAYS")]
+    pub max_historical_days: Option<u32>,
+
+    /// Days for which access remains valid (Nordigen's default is 90)
+    #[arg(long, value_name = "DAYS")]
+    pub access_valid_for_days: Option<u32>,
+
+    /// Access to request: balances, details, transactions (default all)
+    #[arg(long, value_name = "SCOPE", value_delimiter = ',')]
+    pub access_scope: Vec<String>,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank show"))]
+pub struct BankShowCmd {
     /// Bank ID
     pub bank_id: String,
 
-    /// Bank Authorization file
-    #[arg(short, long, required = true, value_name = "FILE")]
+    /// Output bank details as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank capabilities"))]
+pub struct BankCapabilitiesCmd {
+    /// Bank ID
+    pub bank_id: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank status"))]
+pub struct BankStatusCmd {
+    /// Bank Auth State file, or `-` for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub auth: Option<std::path::PathBuf>,
+
+    /// Bank in the banks store, by alias
+    #[arg(long, value_name = "ALIAS", conflicts_with = "auth")]
+    pub bank: Option<String>,
+
+    /// Check every bank of every profile, in banks.json or bank.json,
+    /// each with the profile's own secrets and state
+    #[arg(long, conflicts_with_all = ["auth", "bank"])]
+    pub all_profiles: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank add"))]
+pub struct BankAddCmd {
+    /// Alias to keep the Bank under
+    pub alias: String,
+
+    /// Bank Auth State file to import, or `-` for stdin
+    #[arg(short, long, value_name = "FILE")]
     pub auth: std::path::PathBuf,
+
+    /// Replace the Bank already kept under this alias
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank remove"))]
+pub struct BankRemoveCmd {
+    /// Alias of the Bank to remove
+    pub alias: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank rename"))]
+pub struct BankRenameCmd {
+    /// Current alias
+    pub alias: String,
+    /// New alias
+    pub new_alias: String,
+}
+
+#[derive(Args)]
+#[command()]
+pub struct BankRequisitionCmd {
+    #[command(subcommand)]
+    pub command: BankRequisitionCmds,
+}
+
+#[derive(Subcommand)]
+pub enum BankRequisitionCmds {
+    /// Print the authorization link of an existing requisition
+    Link(BankRequisitionLinkCmd),
+    /// List the accounts linked by a requisition
+    Accounts(BankRequisitionAccountsCmd),
+    /// List all requisitions
+    List(BankRequisitionListCmd),
+    /// Show a requisition
+    Show(BankRequisitionShowCmd),
+    /// Delete requisitions, revoking their access to the accounts
+    Delete(BankRequisitionDeleteCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank requisition link"))]
+pub struct BankRequisitionLinkCmd {
+    /// Requisition ID
+    pub requisition_id: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank requisition accounts"))]
+pub struct BankRequisitionAccountsCmd {
+    /// Requisition ID
+    pub requisition_id: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank requisition list"))]
+pub struct BankRequisitionListCmd {}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank requisition show"))]
+pub struct BankRequisitionShowCmd {
+    /// Requisition ID
+    pub requisition_id: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank requisition delete"))]
+pub struct BankRequisitionDeleteCmd {
+    /// Requisition IDs
+    #[arg(required = true)]
+    pub requisition_ids: Vec<String>,
+}
+
+#[derive(Args)]
+#[command()]
+pub struct BankAgreementCmd {
+    #[command(subcommand)]
+    pub command: BankAgreementCmds,
+}
+
+#[derive(Subcommand)]
+pub enum BankAgreementCmds {
+    /// Create an agreement, to be used when authorizing a bank
+    Create(BankAgreementCreateCmd),
+    /// List all agreements
+    List(BankAgreementListCmd),
+    /// Show an agreement
+    Show(BankAgreementShowCmd),
+    /// Delete agreements
+    Delete(BankAgreementDeleteCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank agreement create"))]
+pub struct BankAgreementCreateCmd {
+    /// Bank ID
+    pub bank_id: String,
+
+    #[command(flatten)]
+    pub terms: AgreementTermsArgs,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank agreement list"))]
+pub struct BankAgreementListCmd {}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank agreement show"))]
+pub struct BankAgreementShowCmd {
+    /// Agreement ID
+    pub agreement_id: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank agreement delete"))]
+pub struct BankAgreementDeleteCmd {
+    /// Agreement IDs
+    #[arg(required = true)]
+    pub agreement_ids: Vec<String>,
 }
 
 #[derive(Args)]
 #[command()]
 pub struct BankAccountCmd {
-    /// Bank Auth State file
-    #[arg(short, long, required = true, value_name = "FILE")]
-    pub auth: std::path::PathBuf,
+    /// Bank Auth State file, or `-` for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub auth: Option<std::path::PathBuf>,
+
+    /// Bank in the banks store, by alias
+    #[arg(long, value_name = "ALIAS", conflicts_with = "auth")]
+    pub bank: Option<String>,
 
     #[command(subcommand)]
     pub command: BankAccountCmds,
@@ -98,21 +615,315 @@ pub enum BankAccountCmds {
     List(BankAccountListCmd),
     Transactions(BankAccountTransactionsCmd),
     Balance(BankAccountBalanceCmd),
+    Show(BankAccountShowCmd),
+    /// Show an account's details, fetched anew from the bank
+    Details(BankAccountDetailsCmd),
 }
 
 #[derive(Args)]
-pub struct BankAccountListCmd {}
+#[command(after_long_help = examples::render("bank account list"))]
+pub struct BankAccountListCmd {
+    /// Fetch the bank's accounts anew, rather than list those cached in
+    /// the bank authorization
+    #[arg(long)]
+    pub refresh_accounts: bool,
+}
 
+/// Selects one account; commands taking it define the `account` group of
+/// these options, required or not.
 #[derive(Args)]
-pub struct BankAccountTransactionsCmd {
+pub struct AccountSelectArgs {
     /// Account IBAN
-    #[arg(short, long, required = true, value_name = "IBAN")]
-    pub iban: String,
+    #[arg(short, long, value_name = "IBAN")]
+    pub iban: Option<String>,
+
+    /// Card account's masked PAN, or its last digits
+    #[arg(short, long, value_name = "PAN")]
+    pub pan: Option<String>,
+
+    /// Account ID
+    #[arg(long, value_name = "ID")]
+    pub account_id: Option<String>,
+
+    /// Account name, display name or product, as the bank reports it
+    #[arg(long, value_name = "NAME")]
+    pub name: Option<String>,
+
+    /// Account currency, to tell apart accounts sharing an IBAN
+    #[arg(long, value_name = "CODE")]
+    pub currency: Option<CurrencyCode>,
+
+    /// Fetch the bank's accounts anew, rather than use those cached in
+    /// the bank authorization
+    #[arg(long)]
+    pub refresh_accounts: bool,
+}
+
+impl AccountSelectArgs {
+    /// Whether an account was selected, other than by currency alone.
+    pub fn is_given(&self) -> bool {
+        self.iban.is_some()
+            || self.pan.is_some()
+            || self.account_id.is_some()
+            || self.name.is_some()
+    }
 }
 
 #[derive(Args)]
+#[command(after_long_help = examples::render("bank account transactions"))]
+#[command(group(
+    ArgGroup::new("account").args(["iban", "pan", "account_id", "name"])
+))]
+pub struct BankAccountTransactionsCmd {
+    // every account of the bank, if none is selected
+    #[command(flatten)]
+    pub account: AccountSelectArgs,
+
+    /// Only the accounts in this group, as configured in `[groups]`; with
+    /// --summary, also sum them up
+    #[arg(short, long, value_name = "NAME", conflicts_with = "account")]
+    pub group: Option<String>,
+
+    /// Only fetch transactions from this date on (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<chrono::NaiveDate>,
+
+    /// Only fetch transactions up to this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<chrono::NaiveDate>,
+
+    /// Show pending transactions instead of booked ones
+    #[arg(long, conflicts_with = "all")]
+    pub pending: bool,
+
+    /// Show pending transactions after booked ones, in their own section
+    #[arg(long)]
+    pub all: bool,
+
+    /// Print count, debits, credits, and net total after the transactions;
+    /// pending transactions are not counted
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Print how much rounding each debit up would have saved
+    #[arg(long)]
+    pub round_up: bool,
+
+    /// Contacts file, to label counterparties
+    #[arg(long, value_name = "FILE")]
+    pub contacts: Option<std::path::PathBuf>,
+
+    /// Handlebars template to render the account and its transactions with
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<std::path::PathBuf>,
+
+    /// Show SEPA creditor id, mandate reference and end-to-end id
+    #[arg(long)]
+    pub sepa: bool,
+
+    /// Only show direct debits from this SEPA creditor id
+    #[arg(long, value_name = "ID")]
+    pub creditor_id: Option<String>,
+
+    /// Only show direct debits with this SEPA mandate reference
+    #[arg(long, value_name = "REF")]
+    pub mandate: Option<String>,
+
+    /// Output format; table adds debit, credit and net totals, csv
+    /// includes pending transactions and all columns, ledger and beancount
+    /// map accounts as per the config's [ledger]
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "text",
+        value_parser = TRANSACTION_FORMATS
+    )]
+    pub format: String,
+
+    /// File to write csv, ledger or beancount output to, instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Split csv output into a file per period (monthly, yearly), named
+    /// after --output, fetching each period on its own
+    #[arg(long, value_name = "PERIOD", requires_all = ["from", "output"])]
+    pub chunk: Option<Chunk>,
+
+    /// Only export booked transactions from after the previous run with
+    /// this option, per account and format, up to yesterday; --from sets
+    /// where accounts never exported start
+    #[arg(
+        long,
+        requires = "output",
+        conflicts_with_all = ["to", "chunk", "pending", "all"]
+    )]
+    pub since_last_export: bool,
+
+    /// With --since-last-export, append to --output rather than write a
+    /// new file named after the last day exported
+    #[arg(long, requires = "since_last_export")]
+    pub append: bool,
+
+    /// Go ahead even if the accounts' remaining daily quota, as last seen,
+    /// won't cover the requests needed
+    #[arg(long)]
+    pub force: bool,
+
+    /// Exit with status 6 when no transactions are found, after writing
+    /// any output, so scripts can tell "nothing new" from a failure
+    #[arg(long, overrides_with = "ok_if_empty")]
+    pub fail_if_empty: bool,
+
+    /// Exit with status 0 when no transactions are found (the default);
+    /// overrides an earlier --fail-if-empty
+    #[arg(long, overrides_with = "fail_if_empty")]
+    pub ok_if_empty: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank account balance"))]
+#[command(group(
+    ArgGroup::new("account")
+        .required(true)
+        .args(["iban", "pan", "account_id", "name", "group"])
+))]
 pub struct BankAccountBalanceCmd {
-    /// Account IBAN
-    #[arg(short, long, required = true, value_name = "IBAN")]
-    pub iban: String,
+    #[command(flatten)]
+    pub account: AccountSelectArgs,
+
+    /// Show the balance of each account in this group, as configured in
+    /// `[groups]`, and their total per currency
+    #[arg(short, long, value_name = "NAME", conflicts_with = "all")]
+    pub group: Option<String>,
+
+    /// Balance type to show, in order of preference (e.g. interimAvailable)
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    pub balance_type: Vec<String>,
+
+    /// Show every balance reported, marking the preferred one
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank account show"))]
+#[command(group(
+    ArgGroup::new("account")
+        .required(true)
+        .args(["iban", "pan", "account_id", "name"])
+))]
+pub struct BankAccountShowCmd {
+    #[command(flatten)]
+    pub account: AccountSelectArgs,
+
+    /// Output account details as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("bank account details"))]
+#[command(group(
+    ArgGroup::new("account")
+        .required(true)
+        .args(["iban", "pan", "account_id", "name"])
+))]
+pub struct BankAccountDetailsCmd {
+    #[command(flatten)]
+    pub account: AccountSelectArgs,
+
+    /// Output the details as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct ContactsCmd {
+    /// Contacts file
+    #[arg(short, long, required = true, value_name = "FILE")]
+    pub file: std::path::PathBuf,
+
+    #[command(subcommand)]
+    pub command: ContactsCmds,
+}
+
+#[derive(Subcommand)]
+pub enum ContactsCmds {
+    /// Add or replace a contact
+    Add(ContactsAddCmd),
+    /// List contacts
+    List(ContactsListCmd),
+    /// Remove a contact
+    Remove(ContactsRemoveCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("contacts add"))]
+pub struct ContactsAddCmd {
+    /// Counterparty IBAN or name
+    pub key: String,
+
+    /// Label to show for the counterparty
+    pub label: String,
+
+    /// Category for the counterparty
+    #[arg(short, long)]
+    pub category: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ContactsListCmd {}
+
+#[derive(Args)]
+pub struct ContactsRemoveCmd {
+    /// Counterparty IBAN or name
+    pub key: String,
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("schema"))]
+pub struct SchemaCmd {
+    /// Model to print the schema of; all of them, keyed by name, if not
+    /// given
+    #[arg(
+        value_name = "MODEL",
+        value_parser = [
+            "account",
+            "details",
+            "balance",
+            "transactions",
+            "institution"
+        ]
+    )]
+    pub model: Option<String>,
+}
+
+#[derive(Args)]
+pub struct FixturesCmd {
+    #[command(subcommand)]
+    pub command: FixturesCmds,
+}
+
+#[derive(Subcommand)]
+pub enum FixturesCmds {
+    /// Rewrite recorded API responses into shareable fixtures
+    Anonymize(FixturesAnonymizeCmd),
+}
+
+#[derive(Args)]
+#[command(after_long_help = examples::render("fixtures anonymize"))]
+pub struct FixturesAnonymizeCmd {
+    /// JSON responses to anonymize, or `-` for stdin
+    #[arg(required = true, value_name = "FILE")]
+    pub files: Vec<std::path::PathBuf>,
+
+    /// Directory to write fixtures to, keeping file names; stdout if not
+    /// given, for a single file
+    #[arg(short, long, value_name = "DIR")]
+    pub output_dir: Option<std::path::PathBuf>,
+
+    /// Seed for the replacements; the same seed always yields the same
+    /// fixtures
+    #[arg(long, default_value = "nordigen-cli")]
+    pub seed: String,
 }
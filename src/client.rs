@@ -0,0 +1,154 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::NaiveDate;
+
+use crate::banks;
+use crate::codes::CountryCode;
+use crate::error::NordigenError;
+
+/// A client for Nordigen's account information API, authenticated with an
+/// access token such as obtained with `nordigen::authorize::authorize`.
+///
+/// Access tokens expire; refreshing them is up to the caller, who then
+/// creates a new client with the new token.
+///
+/// ```no_run
+/// use nordigen_cli::NordigenClient;
+///
+/// # async fn run() -> Result<(), nordigen_cli::NordigenError> {
+/// let client = NordigenClient::new("<ACCESS-TOKEN>");
+/// for account in client.accounts("<REQUISITION-ID>").await? {
+///     let txns = client.transactions(&account.meta.id, None, None).await?;
+///     println!("{}: {} booked", account.identifier(), txns.booked.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NordigenClient {
+    token: String,
+}
+
+impl NordigenClient {
+    pub fn new(token: &str) -> NordigenClient {
+        NordigenClient {
+            token: token.to_string(),
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Institutions available, optionally only those in `country`.
+    pub async fn institutions(
+        &self,
+        country: &Option<CountryCode>,
+    ) -> Result<Vec<banks::Institution>, NordigenError> {
+        banks::list(&self.token, country).await
+    }
+
+    /// An institution's details, including what it supports.
+    pub async fn institution(
+        &self,
+        institution_id: &str,
+    ) -> Result<banks::InstitutionDetails, NordigenError> {
+        banks::institution(&self.token, institution_id).await
+    }
+
+    pub async fn agreements(
+        &self,
+    ) -> Result<Vec<banks::Agreement>, NordigenError> {
+        banks::agreements(&self.token).await
+    }
+
+    pub async fn agreement(
+        &self,
+        agreement_id: &str,
+    ) -> Result<banks::Agreement, NordigenError> {
+        banks::agreement(&self.token, agreement_id).await
+    }
+
+    pub async fn create_agreement(
+        &self,
+        new: &banks::NewAgreement,
+    ) -> Result<banks::Agreement, NordigenError> {
+        banks::create_agreement(&self.token, new).await
+    }
+
+    pub async fn delete_agreement(
+        &self,
+        agreement_id: &str,
+    ) -> Result<(), NordigenError> {
+        banks::delete_agreement(&self.token, agreement_id).await
+    }
+
+    pub async fn requisitions(
+        &self,
+    ) -> Result<Vec<banks::Requisition>, NordigenError> {
+        banks::requisitions(&self.token).await
+    }
+
+    pub async fn requisition(
+        &self,
+        requisition_id: &str,
+    ) -> Result<banks::Requisition, NordigenError> {
+        banks::requisition(&self.token, requisition_id).await
+    }
+
+    /// Create a requisition; the end user authorizes access to their
+    /// accounts by following its link. See `banks::AuthFlow` to wait for
+    /// them to do so.
+    pub async fn create_requisition(
+        &self,
+        new: &banks::NewRequisition,
+    ) -> Result<banks::Requisition, NordigenError> {
+        banks::create_requisition(&self.token, new).await
+    }
+
+    pub async fn delete_requisition(
+        &self,
+        requisition_id: &str,
+    ) -> Result<(), NordigenError> {
+        banks::delete_requisition(&self.token, requisition_id).await
+    }
+
+    /// Metadata and details of every account linked to a requisition.
+    pub async fn accounts(
+        &self,
+        requisition_id: &str,
+    ) -> Result<Vec<banks::Account>, NordigenError> {
+        banks::accounts(&self.token, requisition_id).await
+    }
+
+    pub async fn account(
+        &self,
+        account_id: &str,
+    ) -> Result<banks::Account, NordigenError> {
+        banks::account(&self.token, account_id).await
+    }
+
+    pub async fn balances(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<banks::Balance>, NordigenError> {
+        banks::balances(&self.token, account_id).await
+    }
+
+    /// An account's transactions, optionally only those booked between
+    /// `from` and `to`, inclusive.
+    pub async fn transactions(
+        &self,
+        account_id: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<banks::Transactions, NordigenError> {
+        banks::transactions(&self.token, account_id, from, to).await
+    }
+}
@@ -0,0 +1,66 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Contact {
+    pub label: String,
+    pub category: Option<String>,
+}
+
+/// Friendly labels for counterparties, keyed by IBAN or by name.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Contacts {
+    #[serde(default)]
+    pub entries: BTreeMap<String, Contact>,
+}
+
+/// Normalize a key so lookups ignore case and, for IBANs, spacing.
+fn normalize(key: &str) -> String {
+    key.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+fn normalize_iban(iban: &str) -> String {
+    iban.split_whitespace().collect::<String>().to_uppercase()
+}
+
+impl Contacts {
+    pub fn add(&mut self, key: &str, contact: Contact) {
+        self.entries.insert(normalize(key), contact);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Contact> {
+        self.entries.remove(&normalize(key))
+    }
+
+    /// Find a contact by IBAN first, then by name.
+    pub fn lookup(
+        &self,
+        name: Option<&str>,
+        iban: Option<&str>,
+    ) -> Option<&Contact> {
+        if let Some(iban) = iban {
+            let iban = normalize_iban(iban);
+            let found = self
+                .entries
+                .iter()
+                .find(|(key, _)| normalize_iban(key) == iban)
+                .map(|(_, contact)| contact);
+            if found.is_some() {
+                return found;
+            }
+        }
+        name.and_then(|name| self.entries.get(&normalize(name)))
+    }
+}
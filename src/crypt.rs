@@ -0,0 +1,95 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::io::{Read, Write};
+use std::sync::Mutex;
+
+use age::secrecy::Secret;
+
+/// Passphrase state and bank files are encrypted with, if any.
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+pub fn set_passphrase(passphrase: String) {
+    *PASSPHRASE.lock().unwrap() = Some(passphrase);
+}
+
+fn passphrase() -> Option<Secret<String>> {
+    PASSPHRASE.lock().unwrap().clone().map(Secret::new)
+}
+
+fn is_encrypted(contents: &str) -> bool {
+    contents.trim_start().starts_with(ARMOR_BEGIN)
+}
+
+/// Encrypt `plain` as an ASCII-armored age file if there's a passphrase;
+/// without one, it's written as is.
+pub fn seal(plain: &str) -> Result<String, String> {
+    let passphrase = match passphrase() {
+        None => return Ok(plain.to_string()),
+        Some(res) => res,
+    };
+
+    let encryptor = age::Encryptor::with_user_passphrase(passphrase);
+    let mut out = vec![];
+    let res = age::armor::ArmoredWriter::wrap_output(
+        &mut out,
+        age::armor::Format::AsciiArmor,
+    )
+    .and_then(|armor| {
+        let mut writer = encryptor
+            .wrap_output(armor)
+            .map_err(std::io::Error::other)?;
+        writer.write_all(plain.as_bytes())?;
+        writer.finish()?.finish()
+    });
+    if let Err(err) = res {
+        return Err(format!("Unable to encrypt: {}", err));
+    }
+    String::from_utf8(out).map_err(|err| format!("Unable to encrypt: {}", err))
+}
+
+/// Decrypt `contents` if it's an age file; others are read as they are,
+/// so files written before setting a passphrase keep working.
+pub fn open(contents: &str) -> Result<String, String> {
+    if !is_encrypted(contents) {
+        return Ok(contents.to_string());
+    }
+    let passphrase = match passphrase() {
+        None => {
+            return Err(String::from(
+                "file is encrypted; give --passphrase-file or set \
+                 NORDIGEN_PASSPHRASE",
+            ));
+        }
+        Some(res) => res,
+    };
+
+    let reader = age::armor::ArmoredReader::new(contents.as_bytes());
+    let decryptor = match age::Decryptor::new(reader) {
+        Ok(age::Decryptor::Passphrase(res)) => res,
+        Ok(_) => {
+            return Err(String::from("file is not passphrase-encrypted"));
+        }
+        Err(err) => return Err(format!("Unable to decrypt: {}", err)),
+    };
+    let mut plain = String::new();
+    let res = decryptor
+        .decrypt(&passphrase, None)
+        .map_err(|err| err.to_string())
+        .and_then(|mut reader| {
+            reader
+                .read_to_string(&mut plain)
+                .map_err(|err| err.to_string())
+        });
+    match res {
+        Err(err) => Err(format!("Unable to decrypt: {}", err)),
+        Ok(_) => Ok(plain),
+    }
+}
@@ -0,0 +1,214 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::fmt;
+
+/// Errors from talking to the Nordigen API, and from bank authorization.
+///
+/// Each maps to a process exit code, see `exit_code()`:
+///
+///   1  any other error
+///   3  authentication failed (invalid or expired token, bad secrets)
+///   4  rate limited
+///   5  network error, the API could not be reached
+#[derive(Debug)]
+pub enum NordigenError {
+    /// The request could not be sent, or its response not received.
+    Http(reqwest::Error),
+    /// The API answered with an error status.
+    Api {
+        status: u16,
+        summary: String,
+        detail: String,
+    },
+    /// The API's rate limit was reached; `reset` is how many seconds until
+    /// it resets, if the response said.
+    RateLimited {
+        summary: String,
+        detail: String,
+        reset: Option<u64>,
+    },
+    /// Local I/O, such as listening for the authorization callback.
+    Io(String, std::io::Error),
+    /// A response body that does not match what was expected.
+    Parse(String),
+    /// The end user's bank authorization did not complete.
+    Authorization(String),
+}
+
+/// A wait such as `1h 05m`, `4m 30s` or `12s`.
+fn format_wait(secs: u64) -> String {
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m {:02}s", mins, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn field(value: &serde_json::Value, name: &str) -> String {
+    match value.get(name) {
+        None => String::new(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+impl NordigenError {
+    /// Build from an error response. Nordigen returns a `summary` and
+    /// `detail`, either at the top level or, for invalid fields, keyed by
+    /// the field's name.
+    pub fn from_response(status: u16, body: &str) -> NordigenError {
+        let value: serde_json::Value =
+            serde_json::from_str(body).unwrap_or_default();
+        let entry = if value.get("summary").is_some() {
+            Some(&value)
+        } else {
+            value.as_object().and_then(|fields| {
+                fields.values().find(|v| v.get("summary").is_some())
+            })
+        };
+
+        let (summary, detail) = match entry {
+            None => (body.trim().to_string(), String::new()),
+            Some(entry) => (field(entry, "summary"), field(entry, "detail")),
+        };
+        NordigenError::Api {
+            status,
+            summary,
+            detail,
+        }
+    }
+
+    /// Build from a 429 response, with the seconds until the limit resets.
+    pub fn rate_limited(body: &str, reset: Option<u64>) -> NordigenError {
+        match NordigenError::from_response(429, body) {
+            NordigenError::Api {
+                summary, detail, ..
+            } => NordigenError::RateLimited {
+                summary,
+                detail,
+                reset,
+            },
+            other => other,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            NordigenError::Api { status, .. } => Some(*status),
+            NordigenError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self.status(), Some(401) | Some(403))
+    }
+
+    /// Whether the end user's access to their accounts has run out: the
+    /// requisition or its agreement expired, or was revoked or suspended.
+    /// Unlike an expired access token, this needs a new bank authorization.
+    pub fn is_access_expired(&self) -> bool {
+        let (summary, detail) = match self {
+            NordigenError::Api {
+                status: 400 | 401 | 403 | 409 | 428,
+                summary,
+                detail,
+            } => (summary, detail),
+            _ => return false,
+        };
+        let text = format!("{} {}", summary, detail).to_lowercase();
+        !text.contains("token")
+            && ["expired", "revoked", "suspended"]
+                .iter()
+                .any(|word| text.contains(word))
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(429)
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.is_unauthorized() {
+            3
+        } else if self.is_rate_limited() {
+            4
+        } else if matches!(self, NordigenError::Http(_)) {
+            5
+        } else {
+            1
+        }
+    }
+}
+
+impl fmt::Display for NordigenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NordigenError::Http(err) => {
+                write!(f, "Error sending request: {}", err)
+            }
+            NordigenError::Api {
+                status,
+                summary,
+                detail,
+            } => {
+                write!(f, "API returned {}: {}", status, summary)?;
+                if !detail.is_empty() && detail != summary {
+                    write!(f, " ({})", detail)?;
+                }
+                Ok(())
+            }
+            NordigenError::RateLimited {
+                summary,
+                detail,
+                reset,
+            } => {
+                write!(f, "API rate limit reached: {}", summary)?;
+                if !detail.is_empty() && detail != summary {
+                    write!(f, " ({})", detail)?;
+                }
+                match reset {
+                    None => write!(f, "; try again later"),
+                    Some(secs) => {
+                        write!(f, "; resets in {}", format_wait(*secs))
+                    }
+                }
+            }
+            NordigenError::Io(context, err) => {
+                write!(f, "{}: {}", context, err)
+            }
+            NordigenError::Parse(msg) => {
+                write!(f, "Unable to parse response: {}", msg)
+            }
+            NordigenError::Authorization(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for NordigenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NordigenError::Http(err) => Some(err),
+            NordigenError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NordigenError {
+    fn from(err: reqwest::Error) -> NordigenError {
+        NordigenError::Http(err)
+    }
+}
@@ -0,0 +1,513 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+
+struct Example {
+    /// Command path, without the binary name
+    command: &'static str,
+    about: &'static str,
+    args: &'static str,
+}
+
+const EXAMPLES: &[Example] = &[
+    Example {
+        command: "init",
+        about: "Set up ~/.config/nordigen-cli, entering the key when asked",
+        args: "init --secret-id <ID>",
+    },
+    Example {
+        command: "authorize",
+        about: "Obtain an access token using the secrets in the config file",
+        args: "authorize --config nordigen.cfg --state state.json",
+    },
+    Example {
+        command: "authorize",
+        about: "Same, with config.toml and state.json in one directory",
+        args: "--config-dir ~/.nordigen authorize",
+    },
+    Example {
+        command: "authorize",
+        about: "Same, with ~/.config/nordigen-cli/config.toml, keeping the \
+                state in ~/.local/share/nordigen-cli",
+        args: "authorize",
+    },
+    Example {
+        command: "authorize",
+        about: "Same, keeping the state file encrypted with a passphrase",
+        args: "--passphrase-file ~/.nordigen-passphrase authorize",
+    },
+    Example {
+        command: "refresh",
+        about: "Refresh an expired access token",
+        args: "refresh --state state.json",
+    },
+    Example {
+        command: "rotate-secrets",
+        about: "Switch to a new secret, with the key read from stdin",
+        args: "rotate-secrets --config nordigen.cfg --state state.json \\
+               --secret-id <NEW-ID> < new-key.txt",
+    },
+    Example {
+        command: "secrets store",
+        about: "Keep the secrets in the OS keyring, typing the key when asked",
+        args: "secrets store --secret-id <ID>",
+    },
+    Example {
+        command: "secrets delete",
+        about: "Forget the secrets kept in the OS keyring",
+        args: "secrets delete",
+    },
+    Example {
+        command: "profile create",
+        about: "Keep a business's banks apart from your own, then set it up",
+        args: "profile create business && nordigen-cli --profile business \\
+               init --secret-id <ID>",
+    },
+    Example {
+        command: "profile list",
+        about: "List profiles",
+        args: "profile list",
+    },
+    Example {
+        command: "profile delete",
+        about: "Delete a profile and every file in it, without asking",
+        args: "profile delete business --yes",
+    },
+    Example {
+        command: "bank list",
+        about: "List institutions available in Portugal",
+        args: "bank --state state.json list --country PT",
+    },
+    Example {
+        command: "bank list",
+        about: "Same, without ever writing tokens to disk",
+        args: "--ephemeral bank --config nordigen.cfg list --country PT",
+    },
+    Example {
+        command: "bank list",
+        about: "Same, in CI, with NORDIGEN_SECRET_ID and NORDIGEN_SECRET_KEY \
+                set instead of a config file",
+        args: "--ephemeral bank list --country PT",
+    },
+    Example {
+        command: "bank list",
+        about: "Find Portuguese banks named like caixa, most history first",
+        args: "bank --state state.json list --country PT --search caixa \\
+               --sort days",
+    },
+    Example {
+        command: "bank list",
+        about: "See which Portuguese banks were added, removed or changed \
+                since last listing them",
+        args: "bank --state state.json list --country PT --diff",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize access to a bank's accounts",
+        args: "bank --state state.json authorize SANDBOXFINANCE_SFIN0000 \\
+               --auth bank.json",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize several banks, as listed in a file such as\n  \
+                #   [[bank]]\n  \
+                #   id = \"SANDBOXFINANCE_SFIN0000\"\n  \
+                #   auth = \"sandbox.json\"",
+        args: "bank --state state.json authorize --batch banks.toml",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize from a container, with the redirect on port 8080",
+        args: "bank --state state.json authorize SANDBOXFINANCE_SFIN0000 \\
+               --auth bank.json --redirect-host 0.0.0.0 --redirect-port 8080",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Same, reporting progress as JSON lines on stderr",
+        args: "--progress json bank --state state.json authorize \\
+               --batch banks.toml",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize from a remote server, opening the link elsewhere",
+        args: "bank --state state.json authorize SANDBOXFINANCE_SFIN0000 \\
+               --auth bank.json --no-callback",
+    },
+    Example {
+        command: "bank show",
+        about: "Show a bank's BIC, countries and payment products",
+        args: "bank --state state.json show SANDBOXFINANCE_SFIN0000",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Try out the whole flow against Nordigen's sandbox bank",
+        args: "bank --state state.json authorize --sandbox --auth sandbox.json",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize a bank, keeping it in banks.json as \"savings\"",
+        args: "bank --state state.json authorize SANDBOXFINANCE_SFIN0000 \\
+               --bank savings",
+    },
+    Example {
+        command: "bank add",
+        about: "Keep an existing authorization file in banks.json",
+        args: "bank add checking --auth bank.json",
+    },
+    Example {
+        command: "bank remove",
+        about: "Forget a bank kept in banks.json",
+        args: "bank remove checking",
+    },
+    Example {
+        command: "bank rename",
+        about: "Rename a bank kept in banks.json",
+        args: "bank rename checking current",
+    },
+    Example {
+        command: "bank capabilities",
+        about: "Check what a bank supports before authorizing it",
+        args: "bank --state state.json capabilities SANDBOXFINANCE_SFIN0000",
+    },
+    Example {
+        command: "bank status",
+        about: "Check a bank authorization before fetching with it",
+        args: "bank --state state.json status --auth bank.json",
+    },
+    Example {
+        command: "bank status",
+        about: "Check the banks of every profile, each with its own secrets",
+        args: "bank status --all-profiles",
+    },
+    Example {
+        command: "bank requisition link",
+        about: "Print the authorization link of a pending requisition",
+        args: "bank --state state.json requisition link <REQUISITION-ID>",
+    },
+    Example {
+        command: "bank requisition accounts",
+        about: "Inspect the accounts of a requisition created elsewhere",
+        args: "bank --state state.json requisition accounts <REQUISITION-ID>",
+    },
+    Example {
+        command: "bank requisition list",
+        about: "List all requisitions, including stale ones",
+        args: "bank --state state.json requisition list",
+    },
+    Example {
+        command: "bank requisition show",
+        about: "Show a requisition's status and accounts",
+        args: "bank --state state.json requisition show <REQUISITION-ID>",
+    },
+    Example {
+        command: "bank requisition delete",
+        about: "Delete requisitions that are no longer needed",
+        args: "bank --state state.json requisition delete <ID> <ID>",
+    },
+    Example {
+        command: "bank authorize",
+        about: "Authorize a bank, requesting two years of history",
+        args: "bank --state state.json authorize SANDBOXFINANCE_SFIN0000 \\
+               --auth bank.json --max-historical-days 730",
+    },
+    Example {
+        command: "bank agreement create",
+        about: "Create an agreement for balances only, valid for 30 days",
+        args: "bank --state state.json agreement create \\
+               SANDBOXFINANCE_SFIN0000 --access-valid-for-days 30 \\
+               --access-scope balances",
+    },
+    Example {
+        command: "bank agreement list",
+        about: "List all end user agreements",
+        args: "bank --state state.json agreement list",
+    },
+    Example {
+        command: "bank agreement show",
+        about: "Show an agreement's terms and whether it was accepted",
+        args: "bank --state state.json agreement show <AGREEMENT-ID>",
+    },
+    Example {
+        command: "bank agreement delete",
+        about: "Delete agreements that are no longer needed",
+        args: "bank --state state.json agreement delete <ID>",
+    },
+    Example {
+        command: "bank account list",
+        about: "List accounts of an authorized bank",
+        args: "bank --state state.json account --auth bank.json list",
+    },
+    Example {
+        command: "bank account list",
+        about: "Same, for a bank kept in banks.json",
+        args: "bank --state state.json account --bank savings list",
+    },
+    Example {
+        command: "bank account list",
+        about: "Same, for that bank in the business profile",
+        args: "--profile business bank account --bank savings list",
+    },
+    Example {
+        command: "bank account list",
+        about: "Same, on a monitoring host that must not change anything",
+        args: "--read-only bank --state state.json account --auth bank.json \\
+               list",
+    },
+    Example {
+        command: "bank account show",
+        about: "Show details for a card account, by its PAN's last digits",
+        args: "bank --state state.json account --auth bank.json show \\
+               --pan 1234",
+    },
+    Example {
+        command: "bank account balance",
+        about: "Show a credit card's balance, selecting it by name",
+        args: "bank --state state.json account --auth bank.json balance \\
+               --name \"Gold Card\"",
+    },
+    Example {
+        command: "bank account show",
+        about: "Show details for an account the bank added since last time",
+        args: "bank --state state.json account --auth bank.json show \\
+               --iban PT50000000000000000000001 --refresh-accounts",
+    },
+    Example {
+        command: "bank account show",
+        about: "Show account details as JSON",
+        args: "bank --state state.json account --auth bank.json show \\
+               --iban PT50000000000000000000000 --json",
+    },
+    Example {
+        command: "bank account details",
+        about: "Show what the bank reports of a credit card, e.g. its linked \
+                account",
+        args: "bank --state state.json account --auth bank.json details \\
+               --pan 1234",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "List an account's transactions",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "List only January's transactions",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --from 2023-01-01 --to 2023-01-31",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "List booked transactions, then those still pending",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 --all",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Same, as a table with debit, credit and net totals",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 --all \\
+               --format table",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "List transactions of every account of a bank, per account",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Sum up January's spending across the credit group",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --group credit --from 2023-01-01 \\
+               --to 2023-01-31 --summary",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Export booked and pending transactions for a spreadsheet",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --format csv --output transactions.csv",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Export years of history into transactions-YYYY-MM.csv files",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --format csv --output transactions.csv \\
+               --from 2020-01-01 --chunk monthly",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Daily, export what each account booked since the last run \
+                into transactions-YYYY-MM-DD.csv",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --format csv --output transactions.csv \\
+               --since-last-export",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Import transactions into beancount, as mapped in [ledger]",
+        args: "--config-dir ~/.nordigen bank account transactions \\
+               --iban PT50000000000000000000000 --contacts contacts.json \\
+               --format beancount --output bank.beancount",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Same, appending only what's new to the beancount file",
+        args: "--config-dir ~/.nordigen bank account transactions \\
+               --iban PT50000000000000000000000 --contacts contacts.json \\
+               --format beancount --output bank.beancount \\
+               --since-last-export --append",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "In a cron job, exit with status 6 if nothing came in today",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --from \"$(date +%F)\" --fail-if-empty",
+    },
+    Example {
+        command: "bank account balance",
+        about: "Show an account's balance, preferring the closing balance",
+        args: "bank --state state.json account --auth bank.json balance \\
+               --iban PT50000000000000000000000 --type closingBooked",
+    },
+    Example {
+        command: "bank account balance",
+        about: "Show the balances of the accounts in [groups], and their total",
+        args: "bank --state state.json account --auth bank.json balance \\
+               --group liquid",
+    },
+    Example {
+        command: "bank account balance",
+        about: "Show all of an account's balances",
+        args: "bank --state state.json account --auth bank.json balance \
+               --iban PT50000000000000000000000 --all",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "List transactions, labelling counterparties from contacts",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --contacts contacts.json",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Render transactions through a custom template",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --template report.hbs",
+    },
+    Example {
+        command: "bank account transactions",
+        about: "Find direct debits for a given SEPA mandate",
+        args: "bank --state state.json account --auth bank.json \\
+               transactions --iban PT50000000000000000000000 \\
+               --sepa --mandate MANDATE-0001",
+    },
+    Example {
+        command: "contacts add",
+        about: "Label a counterparty by IBAN",
+        args: "contacts --file contacts.json add \\
+               PT50000000000000000000000 Landlord --category housing",
+    },
+    Example {
+        command: "contacts add",
+        about: "Label a counterparty by name",
+        args: "contacts --file contacts.json add \"NFLX SUBSCR\" Netflix",
+    },
+    Example {
+        command: "schema",
+        about: "Print the schema of `bank account show --json`'s output",
+        args: "schema account",
+    },
+    Example {
+        command: "fixtures anonymize",
+        about: "Anonymize recorded responses into the fixtures directory",
+        args: "fixtures anonymize accounts.json transactions.json \\
+               --output-dir tests/fixtures",
+    },
+];
+
+/// Render the examples for `command` as a help section, to be shown with
+/// `--help`.
+pub fn render(command: &str) -> String {
+    let mut out = String::from("Examples:\n");
+    for example in EXAMPLES.iter().filter(|e| e.command == command) {
+        out.push_str(&format!(
+            "  # {}\n  $ nordigen-cli {}\n\n",
+            example.about, example.args
+        ));
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::EXAMPLES;
+    use crate::cli::Cli;
+
+    /// Split `args` the way a shell would, as far as the examples go: one
+    /// list of words per command chained with `&&`, leaving out redirects
+    /// and standing in a fixed date for `$(date +%F)`.
+    fn commands(args: &str) -> Vec<Vec<String>> {
+        let args = args
+            .replace("\\\n", " ")
+            .replace("$(date +%F)", "2023-01-31");
+        let mut words: Vec<String> = Vec::new();
+        let mut word: Option<String> = None;
+        let mut quoted = false;
+        for c in args.chars().chain(std::iter::once(' ')) {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    word.get_or_insert_with(String::new);
+                }
+                c if c.is_whitespace() && !quoted => {
+                    words.extend(word.take());
+                }
+                c => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+
+        words
+            .split(|word| word == "&&")
+            .map(|words| {
+                let mut command = vec![String::from("nordigen-cli")];
+                let mut words =
+                    words.iter().skip_while(|w| *w == "nordigen-cli");
+                while let Some(word) = words.next() {
+                    if word == "<" {
+                        words.next();
+                    } else {
+                        command.push(word.clone());
+                    }
+                }
+                command
+            })
+            .collect()
+    }
+
+    #[test]
+    fn examples_parse() {
+        for example in EXAMPLES {
+            for command in commands(example.args) {
+                if let Err(err) = Cli::try_parse_from(&command) {
+                    panic!(
+                        "example `{}` fails to parse: {}",
+                        example.args, err
+                    );
+                }
+            }
+        }
+    }
+}
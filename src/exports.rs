@@ -0,0 +1,34 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// The last day exported of each account, per format, as kept in
+/// exports.json for `--since-last-export`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Watermarks {
+    exported: BTreeMap<String, NaiveDate>,
+}
+
+fn key(account_id: &str, format: &str) -> String {
+    format!("{}/{}", account_id, format)
+}
+
+impl Watermarks {
+    pub fn get(&self, account_id: &str, format: &str) -> Option<NaiveDate> {
+        self.exported.get(&key(account_id, format)).copied()
+    }
+
+    pub fn set(&mut self, account_id: &str, format: &str, date: NaiveDate) {
+        self.exported.insert(key(account_id, format), date);
+    }
+}
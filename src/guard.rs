@@ -0,0 +1,29 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether operations creating or deleting remote resources, or writing
+/// local auth state, are refused.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Exit rather than `what`, when in read-only mode.
+pub fn deny_or_exit(what: &str) {
+    if is_read_only() {
+        eprintln!("Refusing to {} in read-only mode.", what);
+        std::process::exit(1);
+    }
+}
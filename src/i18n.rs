@@ -0,0 +1,221 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    En = 0,
+    Pt = 1,
+}
+
+impl std::str::FromStr for Lang {
+    type Err = String;
+
+    /// Accepts both plain language codes and POSIX locales, such as
+    /// `pt_PT.UTF-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or("").to_lowercase();
+        match code.as_str() {
+            "en" | "c" | "posix" => Ok(Lang::En),
+            "pt" => Ok(Lang::Pt),
+            _ => Err(format!("unsupported language '{}'; try 'en' or 'pt'", s)),
+        }
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Pt,
+        _ => Lang::En,
+    }
+}
+
+/// Obtain the language from the environment, as per POSIX locale rules.
+pub fn detect() -> Lang {
+    for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+        if let Ok(value) = std::env::var(var) {
+            if value.is_empty() {
+                continue;
+            }
+            return value.parse().unwrap_or(Lang::En);
+        }
+    }
+    Lang::En
+}
+
+#[derive(Clone, Copy)]
+pub enum Msg {
+    StateNotFound,
+    StateInvalid,
+    UnknownError,
+    RefreshExpired,
+    RefreshExpiredAuthorize,
+    AccessExpiredRefresh,
+    AuthorizationValid,
+    TokenStillValid,
+    ObtainedToken,
+    RefreshedToken,
+    EphemeralToken,
+    EphemeralRefresh,
+    FollowLink,
+    BankAuthorized,
+    RequisitionStatus,
+    AccountNotFound,
+    AccountAmbiguous,
+    AccountDisambiguate,
+    NoBalances,
+    ContactNotFound,
+    FirstRunConfig,
+    FirstRunSecrets,
+    FirstRunInit,
+    FirstRunState,
+    FirstRunAuthorize,
+}
+
+/// Message catalog, one entry per language, indexed by `Lang`.
+fn catalog(msg: Msg) -> [&'static str; 2] {
+    match msg {
+        Msg::StateNotFound => [
+            "State file not found",
+            "Ficheiro de estado não encontrado",
+        ],
+        Msg::StateInvalid => [
+            "Invalid state file found",
+            "Ficheiro de estado inválido",
+        ],
+        Msg::UnknownError => [
+            "Unknown error!",
+            "Erro desconhecido!",
+        ],
+        Msg::RefreshExpired => [
+            "Refresh token has expired!",
+            "O token de renovação expirou!",
+        ],
+        Msg::RefreshExpiredAuthorize => [
+            "Refresh token has expired. Please authorize again.",
+            "O token de renovação expirou. Por favor autorize novamente.",
+        ],
+        Msg::AccessExpiredRefresh => [
+            "Access token expired. Please refresh!",
+            "O token de acesso expirou. Por favor renove-o!",
+        ],
+        Msg::AuthorizationValid => [
+            "Authorization still valid",
+            "Autorização ainda válida",
+        ],
+        Msg::TokenStillValid => [
+            "Token is still valid and does not need to be refreshed.",
+            "O token ainda é válido e não precisa de ser renovado.",
+        ],
+        Msg::ObtainedToken => [
+            "Obtained authorization token; expires on {}",
+            "Token de autorização obtido; expira em {}",
+        ],
+        Msg::RefreshedToken => [
+            "Successfully refreshed; new token expires on {}",
+            "Renovado com sucesso; o novo token expira em {}",
+        ],
+        Msg::EphemeralToken => [
+            "Obtained authorization token; expires on {} (not stored)",
+            "Token de autorização obtido; expira em {} (não guardado)",
+        ],
+        Msg::EphemeralRefresh => [
+            "Nothing to refresh: ephemeral mode authorizes on every run.",
+            "Nada a renovar: o modo efémero autoriza em cada execução.",
+        ],
+        Msg::FollowLink => [
+            "Please follow the link below to authenticate with the selected bank.",
+            "Por favor siga a ligação abaixo para se autenticar junto do banco selecionado.",
+        ],
+        Msg::BankAuthorized => [
+            "Successfully authorized with bank!",
+            "Autorizado com sucesso junto do banco!",
+        ],
+        Msg::RequisitionStatus => [
+            "Requisition has status {}; its link may no longer be usable.",
+            "A requisição tem o estado {}; a sua ligação poderá já não ser utilizável.",
+        ],
+        Msg::AccountNotFound => [
+            "Could not find account with {}",
+            "Não foi possível encontrar uma conta com {}",
+        ],
+        Msg::AccountAmbiguous => [
+            "Multiple accounts found with {}:",
+            "Foram encontradas várias contas com {}:",
+        ],
+        Msg::AccountDisambiguate => [
+            "Please select one with --currency, --name or --account-id.",
+            "Por favor selecione uma com --currency, --name ou --account-id.",
+        ],
+        Msg::NoBalances => [
+            "No balances reported for account",
+            "O banco não reportou saldos para a conta",
+        ],
+        Msg::ContactNotFound => [
+            "No contact found for {}",
+            "Nenhum contacto encontrado para {}",
+        ],
+        Msg::FirstRunConfig => [
+            "No config file found at {}. To get started:",
+            "Nenhum ficheiro de configuração encontrado em {}. Para começar:",
+        ],
+        Msg::FirstRunSecrets => [
+            "Create API secrets at {}",
+            "Crie segredos de API em {}",
+        ],
+        Msg::FirstRunInit => [
+            "Run `{}` and enter the secret key when asked",
+            "Execute `{}` e introduza a chave secreta quando pedida",
+        ],
+        Msg::FirstRunState => [
+            "Not authorized yet: no state file at {}. To get started:",
+            "Ainda sem autorização: nenhum ficheiro de estado em {}. Para começar:",
+        ],
+        Msg::FirstRunAuthorize => [
+            "Run `{}`",
+            "Execute `{}`",
+        ],
+    }
+}
+
+/// Translate `msg`, replacing each `{}` placeholder with `args` in order.
+pub fn text(msg: Msg, args: &[String]) -> String {
+    let template = catalog(msg)[lang() as usize];
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = template.split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(arg) = args.next() {
+                out.push_str(arg);
+            }
+        }
+    }
+    out
+}
+
+/// Translate a catalog message, formatting any arguments with `Display`.
+macro_rules! tr {
+    ($msg:ident) => {
+        $crate::i18n::text($crate::i18n::Msg::$msg, &[])
+    };
+    ($msg:ident, $($arg:expr),+) => {
+        $crate::i18n::text(
+            $crate::i18n::Msg::$msg,
+            &[$($arg.to_string()),+],
+        )
+    };
+}
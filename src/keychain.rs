@@ -0,0 +1,108 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::sync::Mutex;
+
+/// Service the secrets are kept under in the OS keyring: Secret Service,
+/// Keychain, or Windows Credential Manager.
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "nordigen-cli";
+
+/// Profile whose secrets to use, each kept under its own service.
+static PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_profile(name: &str) {
+    *PROFILE.lock().unwrap() = Some(name.to_string());
+}
+
+#[cfg(feature = "keyring")]
+fn service() -> String {
+    match PROFILE.lock().unwrap().as_ref() {
+        None => String::from(SERVICE),
+        Some(profile) => format!("{}/{}", SERVICE, profile),
+    }
+}
+
+/// Names of the entries, matching the config file's keys.
+pub const SECRET_ID: &str = "secret_id";
+pub const SECRET_KEY: &str = "secret_key";
+
+/// Set to leave the keyring alone altogether, as if there were none;
+/// for when the secrets kept there aren't the ones to use, e.g. in tests.
+#[cfg(feature = "keyring")]
+const NO_KEYRING_VAR: &str = "NORDIGEN_NO_KEYRING";
+
+#[cfg(feature = "keyring")]
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+    if std::env::var_os(NO_KEYRING_VAR).is_some() {
+        return Err(format!("Keyring disabled by {}", NO_KEYRING_VAR));
+    }
+    keyring::Entry::new(&service(), name)
+        .map_err(|err| format!("Unable to access keyring: {}", err))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn entry(_name: &str) -> Result<NoKeyring, String> {
+    Err(String::from("Built without keyring support"))
+}
+
+/// Stands in for `keyring::Entry` when built without the `keyring`
+/// feature; never constructed.
+#[cfg(not(feature = "keyring"))]
+enum NoKeyring {}
+
+#[cfg(not(feature = "keyring"))]
+impl NoKeyring {
+    fn get_password(&self) -> Result<String, String> {
+        match *self {}
+    }
+
+    fn set_password(&self, _value: &str) -> Result<(), String> {
+        match *self {}
+    }
+
+    fn delete_password(&self) -> Result<(), String> {
+        match *self {}
+    }
+}
+
+/// The secret stored as `name`, if any. A keyring that can't be reached
+/// counts as having none, so commands work the same without one.
+pub fn get(name: &str) -> Option<String> {
+    entry(name).ok()?.get_password().ok()
+}
+
+/// Whether both secrets are in the keyring.
+pub fn has_secrets() -> bool {
+    get(SECRET_ID).is_some() && get(SECRET_KEY).is_some()
+}
+
+pub fn store(secret_id: &str, secret_key: &str) -> Result<(), String> {
+    for (name, value) in [(SECRET_ID, secret_id), (SECRET_KEY, secret_key)] {
+        if let Err(err) = entry(name)?.set_password(value) {
+            return Err(format!("Unable to store {}: {}", name, err));
+        }
+    }
+    Ok(())
+}
+
+/// Remove both secrets, returning whether there were any.
+pub fn delete() -> Result<bool, String> {
+    let mut found = false;
+    for name in [SECRET_ID, SECRET_KEY] {
+        match entry(name)?.delete_password() {
+            Ok(()) => found = true,
+            #[cfg(feature = "keyring")]
+            Err(keyring::Error::NoEntry) => {}
+            Err(err) => {
+                return Err(format!("Unable to delete {}: {}", name, err));
+            }
+        }
+    }
+    Ok(found)
+}
@@ -0,0 +1,79 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use chrono::NaiveDate;
+
+/// Plain text accounting formats transactions may be exported to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dialect {
+    Ledger,
+    Beancount,
+}
+
+/// One transaction, as a two-posting entry: the amount goes to `account`,
+/// and is balanced by `counter_account`.
+pub struct Entry {
+    pub date: NaiveDate,
+    pub pending: bool,
+    pub payee: String,
+    pub narration: Option<String>,
+    pub amount: String,
+    pub currency: String,
+    pub account: String,
+    pub counter_account: String,
+}
+
+/// Make `name` usable as an account name component, in both dialects.
+pub fn component(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if !out.starts_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        out.insert(0, 'X');
+    }
+    out
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn render(dialect: Dialect, entry: &Entry) -> String {
+    let flag = if entry.pending { "!" } else { "*" };
+    let header = match dialect {
+        Dialect::Ledger => {
+            let mut line = format!(
+                "{} {} {}",
+                entry.date.format("%Y/%m/%d"),
+                flag,
+                entry.payee.replace(';', ",")
+            );
+            if let Some(narration) = &entry.narration {
+                line.push_str(&format!("  ; {}", narration));
+            }
+            line
+        }
+        Dialect::Beancount => format!(
+            "{} {} {} {}",
+            entry.date.format("%Y-%m-%d"),
+            flag,
+            quoted(&entry.payee),
+            quoted(entry.narration.as_deref().unwrap_or(""))
+        ),
+    };
+    format!(
+        "{}\n    {}  {} {}\n    {}\n",
+        header,
+        entry.account,
+        entry.amount,
+        entry.currency,
+        entry.counter_account
+    )
+}
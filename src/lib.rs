@@ -0,0 +1,31 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+
+//! Client for Nordigen's open banking API, as used by `nordigen-cli`.
+//!
+//! `NordigenClient` covers institutions, agreements, requisitions and
+//! accounts; `banks` has the models and the functions behind it, along
+//! with `banks::AuthFlow` to walk an end user through a bank's
+//! authorization. Errors are `NordigenError`s.
+
+#[macro_use]
+pub mod msg;
+
+mod api;
+pub mod banks;
+mod client;
+pub mod codes;
+pub mod error;
+mod fuzzy;
+pub mod money;
+pub mod trace;
+
+pub use api::{add_quotas, quota, quotas, set_base_url, Quota};
+pub use client::NordigenClient;
+pub use error::NordigenError;
@@ -0,0 +1,62 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::Level;
+
+/// Level to log at, if at all: `--log-level` if given, otherwise one more
+/// per `-v`; with `--log-file` alone, debug.
+fn level(
+    verbose: u8,
+    level: &Option<String>,
+    file: &Option<PathBuf>,
+) -> Option<Level> {
+    if let Some(level) = level {
+        return level.parse().ok();
+    }
+    match verbose {
+        0 if file.is_some() => Some(Level::DEBUG),
+        0 => None,
+        1 => Some(Level::INFO),
+        2 => Some(Level::DEBUG),
+        _ => Some(Level::TRACE),
+    }
+}
+
+/// Log diagnostics to stderr, or `file`, as per `-v`, `--log-level` and
+/// `--log-file`; nothing is logged without any of them.
+pub fn init(
+    verbose: u8,
+    log_level: &Option<String>,
+    file: &Option<PathBuf>,
+) -> Result<(), String> {
+    let level = match level(verbose, log_level, file) {
+        None => return Ok(()),
+        Some(level) => level,
+    };
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+    let res = match file {
+        None => builder.with_writer(std::io::stderr).try_init(),
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|err| {
+                format!(
+                    "Unable to open log file at {}: {}",
+                    path.display(),
+                    err
+                )
+            })?;
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .try_init()
+        }
+    };
+    res.map_err(|err| format!("Unable to set up logging: {}", err))
+}
@@ -7,22 +7,91 @@
 // (at your option) any later version.
 //
 use clap::Parser;
-use std::io::ErrorKind;
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, IsTerminal, Read, Write};
 
+#[macro_use]
+extern crate nordigen_cli;
+
+#[macro_use]
+mod i18n;
+
+mod bankstore;
+mod catalog;
+mod chunks;
 pub mod cli;
+mod contacts;
+mod crypt;
+mod examples;
+mod exports;
+mod fixtures;
+mod guard;
+mod keychain;
+mod ledger;
+mod logging;
+mod onboarding;
+mod paths;
+mod profiles;
+mod quirks;
+mod quota;
+mod schema;
+mod session;
+mod settings;
+mod summary;
+mod version;
 
+use banks::BankAuthState;
+use bankstore::BankStore;
+use catalog::Catalog;
+use cli::{
+    AccountSelectArgs, BankAccountCmds, BankAccountDetailsCmd,
+    BankAccountListCmd, BankAccountShowCmd, BankAccountTransactionsCmd,
+    BankRequisitionAccountsCmd, BankRequisitionCmds, BankRequisitionDeleteCmd,
+    BankRequisitionLinkCmd, BankRequisitionShowCmd,
+};
+use cli::{
+    AgreementTermsArgs, BankAgreementCmds, BankAgreementCreateCmd,
+    BankAgreementDeleteCmd, BankAgreementShowCmd,
+};
+use cli::{
+    BankAccountBalanceCmd, BankAuthorizeCmd, BankCapabilitiesCmd, BankCmds,
+    BankListCmd, BankShowCmd, Cli, Commands, InitCmd, RotateSecretsCmd,
+};
+use cli::{BankAddCmd, BankCmd, BankRemoveCmd, BankRenameCmd};
+use cli::{ContactsAddCmd, ContactsCmd, ContactsCmds, ContactsRemoveCmd};
+use cli::{FixturesAnonymizeCmd, FixturesCmds, SchemaCmd};
 use cli::{
-    AuthorizeCmd, BankAccountBalanceCmd, BankAuthorizeCmd, BankCmds,
-    BankListCmd, Cli, Commands, RefreshCmd,
+    ProfileCmd, ProfileCmds, ProfileCreateCmd, ProfileDeleteCmd, SecretsCmds,
+    SecretsStoreCmd,
 };
-use cli::{BankAccountCmds, BankAccountTransactionsCmd};
-use nordigen::banks::BankAuthState;
+use contacts::Contacts;
+use exports::Watermarks;
+use futures::stream::{FuturesUnordered, StreamExt};
+use nordigen::authorize;
 use nordigen::config::NordigenConfig;
 use nordigen::state::NordigenState;
-use nordigen::{authorize, banks};
+use nordigen_cli::money::Amount;
+use nordigen_cli::msg::{self, Progress};
+use nordigen_cli::{banks, trace, NordigenError};
 use prettytable::{row, Attr, Cell, Row, Table};
+use quirks::Quirks;
+use session::SessionManager;
+use settings::Settings;
+
+/// A path of `-` stands for stdin when reading, and stdout when writing.
+fn is_stdio(path: &std::path::Path) -> bool {
+    path.as_os_str() == "-"
+}
 
 fn read_file(path: &std::path::PathBuf) -> Result<String, String> {
+    if is_stdio(path) {
+        let mut contents = String::new();
+        return match std::io::stdin().read_to_string(&mut contents) {
+            Err(error) => Err(format!("Error reading stdin: {}", error)),
+            Ok(_) => Ok(contents),
+        };
+    }
+
     if !path.exists() {
         return Err(format!("file at {} does not exist!", path.display()));
     }
@@ -40,8 +109,66 @@ fn read_file(path: &std::path::PathBuf) -> Result<String, String> {
     Ok(contents)
 }
 
+fn create_file(
+    path: &std::path::PathBuf,
+) -> Result<Box<dyn std::io::Write>, std::io::Error> {
+    if is_stdio(path) {
+        return Ok(Box::new(std::io::stdout()));
+    }
+    // default locations may not have been created yet
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(Box::new(std::fs::File::create(path)?))
+}
+
+/// As `create_file`, appending to the file if it exists.
+fn append_file(
+    path: &std::path::PathBuf,
+) -> Result<Box<dyn std::io::Write>, std::io::Error> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    Ok(Box::new(file))
+}
+
+/// As `create_file`, for files holding secrets: readable by us alone from
+/// the start, even when replacing a file that wasn't.
+fn create_private_file(
+    path: &std::path::PathBuf,
+) -> Result<std::fs::File, std::io::Error> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    // an existing file keeps its mode when opened
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
 fn parse_state(path: &std::path::PathBuf) -> Result<NordigenState, String> {
-    let contents = match read_file(path) {
+    let contents = match read_file(path).and_then(|val| crypt::open(&val)) {
         Err(err) => {
             return Err(format!("Error reading state file: {}", err));
         }
@@ -71,7 +198,17 @@ fn write_state(
     let state: NordigenState =
         NordigenState::new(token, token_ttl, refresh, refresh_ttl);
 
-    let buffer = match std::fs::File::create(path) {
+    let contents = serde_json::to_string_pretty(&state)
+        .map_err(|err| err.to_string())
+        .and_then(|val| crypt::seal(&val));
+    let contents = match contents {
+        Err(err) => {
+            return Err(format!("Unable to write state to disk: {}", err));
+        }
+        Ok(res) => res,
+    };
+
+    let mut buffer = match create_file(path) {
         Err(err) => {
             return Err(format!(
                 "Unable to open state file for writing: {}",
@@ -81,39 +218,137 @@ fn write_state(
         Ok(res) => res,
     };
 
-    match serde_json::to_writer_pretty(buffer, &state) {
-        Err(err) => {
-            return Err(format!("Unable to write state to disk: {}", err));
-        }
-        Ok(_) => {}
-    };
+    if let Err(err) = buffer.write_all(contents.as_bytes()) {
+        return Err(format!("Unable to write state to disk: {}", err));
+    }
 
     Ok(state)
 }
 
+const SECRET_ID_VAR: &str = "NORDIGEN_SECRET_ID";
+const SECRET_KEY_VAR: &str = "NORDIGEN_SECRET_KEY";
+const PASSPHRASE_VAR: &str = "NORDIGEN_PASSPHRASE";
+
+fn secret_from_env(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|val| !val.is_empty())
+}
+
+/// Whether secrets can be had without `path`, or from it.
+fn has_config(path: &std::path::Path) -> bool {
+    is_stdio(path)
+        || path.exists()
+        || (secret_from_env(SECRET_ID_VAR).is_some()
+            && secret_from_env(SECRET_KEY_VAR).is_some())
+        || keychain::has_secrets()
+}
+
+/// Guide a first run through setting up whatever's missing, rather than
+/// failing on it; `state` is `None` for commands not needing one.
+fn check_first_run(config: &std::path::Path, state: Option<&std::path::Path>) {
+    let state = match state {
+        Some(path) if !is_stdio(path) && !path.exists() => Some(path),
+        Some(_) => return,
+        None => None,
+    };
+    if !has_config(config) {
+        onboarding::exit(&onboarding::Missing::Config(config));
+    }
+    if let Some(state) = state {
+        onboarding::exit(&onboarding::Missing::State { config, state });
+    }
+}
+
+/// Parse the config file, with secrets in the environment taking
+/// precedence over its own, and those in the keyring filling in for any
+/// it lacks. With both secrets in the environment or the keyring, the
+/// file need not exist.
 fn parse_config(path: &std::path::PathBuf) -> Result<NordigenConfig, String> {
+    let secret_id = secret_from_env(SECRET_ID_VAR);
+    let secret_key = secret_from_env(SECRET_KEY_VAR);
+
+    let missing = !is_stdio(path) && !path.exists();
+    let mut table = if missing
+        && ((secret_id.is_some() && secret_key.is_some())
+            || keychain::has_secrets())
+    {
+        toml::value::Table::new()
+    } else {
+        let contents = match read_file(path) {
+            Err(err) => {
+                return Err(format!("Error reading config file: {}", err));
+            }
+            Ok(val) => val,
+        };
+        match toml::from_str(&contents) {
+            Ok(res) => res,
+            Err(error) => {
+                return Err(format!(
+                    "Unable to parse config file at path {}: {}",
+                    path.display(),
+                    error
+                ));
+            }
+        }
+    };
+
+    if let Some(id) = secret_id {
+        table.insert(String::from("secret_id"), toml::Value::from(id));
+    }
+    if let Some(key) = secret_key {
+        table.insert(String::from("secret_key"), toml::Value::from(key));
+    }
+    for name in [keychain::SECRET_ID, keychain::SECRET_KEY] {
+        if table.contains_key(name) {
+            continue;
+        }
+        if let Some(secret) = keychain::get(name) {
+            table.insert(String::from(name), toml::Value::from(secret));
+        }
+    }
+
+    match toml::Value::Table(table).try_into() {
+        Ok(cfg) => Ok(cfg),
+        Err(error) => Err(format!(
+            "Unable to parse config file at path {}: {}",
+            path.display(),
+            error
+        )),
+    }
+}
+
+fn parse_settings(path: &std::path::PathBuf) -> Result<Settings, String> {
     let contents = match read_file(path) {
         Err(err) => {
             return Err(format!("Error reading config file: {}", err));
         }
         Ok(val) => val,
     };
-    let config: NordigenConfig = match toml::from_str(&contents) {
-        Ok(cfg) => cfg,
+    let settings: Settings = match toml::from_str(&contents) {
+        Ok(res) => res,
         Err(error) => {
             return Err(format!(
-                "Unable to parse config file at path {}: {}",
+                "Unable to parse settings in config file at {}: {}",
                 path.display(),
                 error
             ));
         }
     };
 
-    Ok(config)
+    Ok(settings)
+}
+
+fn get_settings_or_exit(path: &Option<std::path::PathBuf>) -> Settings {
+    match path {
+        None => Settings::default(),
+        Some(path) => parse_settings(path).unwrap_or_else(|err| {
+            eprintln!("Error parsing config: {}", err);
+            std::process::exit(1);
+        }),
+    }
 }
 
 fn parse_bank(path: &std::path::PathBuf) -> Result<BankAuthState, String> {
-    let contents = match read_file(path) {
+    let contents = match read_file(path).and_then(|val| crypt::open(&val)) {
         Err(err) => {
             return Err(format!("Error reading bank file: {}", err));
         }
@@ -137,7 +372,17 @@ fn write_bank<'a>(
     auth: &'a BankAuthState,
     path: &std::path::PathBuf,
 ) -> Result<&'a BankAuthState, String> {
-    let buffer = match std::fs::File::create(path) {
+    let contents = serde_json::to_string_pretty(auth)
+        .map_err(|err| err.to_string())
+        .and_then(|val| crypt::seal(&val));
+    let contents = match contents {
+        Err(err) => {
+            return Err(format!("Unable to write bank state to disk: {}", err));
+        }
+        Ok(res) => res,
+    };
+
+    let mut buffer = match create_file(path) {
         Err(err) => {
             return Err(format!(
                 "Unable to open bank state file for writing: {}",
@@ -147,427 +392,4135 @@ fn write_bank<'a>(
         Ok(res) => res,
     };
 
-    match serde_json::to_writer_pretty(buffer, auth) {
+    if let Err(err) = buffer.write_all(contents.as_bytes()) {
+        return Err(format!("Unable to write bank state to disk: {}", err));
+    }
+    Ok(auth)
+}
+
+fn parse_bank_store(path: &std::path::PathBuf) -> Result<BankStore, String> {
+    if !is_stdio(path) && !path.exists() {
+        return Ok(BankStore::default());
+    }
+    let contents = read_file(path).and_then(|val| crypt::open(&val))?;
+    serde_json::from_str(&contents).map_err(|err| err.to_string())
+}
+
+fn write_bank_store(
+    store: &BankStore,
+    path: &std::path::PathBuf,
+) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(store)
+        .map_err(|err| err.to_string())
+        .and_then(|val| crypt::seal(&val))?;
+    let mut buffer = create_file(path).map_err(|err| err.to_string())?;
+    buffer
+        .write_all(contents.as_bytes())
+        .map_err(|err| err.to_string())
+}
+
+fn parse_catalog(path: &std::path::PathBuf) -> Result<Catalog, String> {
+    if !path.exists() {
+        return Ok(Catalog::default());
+    }
+    let contents = read_file(path)?;
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))
+}
+
+fn write_catalog(
+    catalog: &Catalog,
+    path: &std::path::PathBuf,
+) -> Result<(), String> {
+    let buffer = create_file(path).map_err(|err| err.to_string())?;
+    serde_json::to_writer(buffer, catalog)
+        .map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+}
+
+fn parse_watermarks(path: &std::path::PathBuf) -> Result<Watermarks, String> {
+    if !path.exists() {
+        return Ok(Watermarks::default());
+    }
+    let contents = read_file(path)?;
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))
+}
+
+fn write_watermarks(
+    watermarks: &Watermarks,
+    path: &std::path::PathBuf,
+) -> Result<(), String> {
+    let buffer = create_file(path).map_err(|err| err.to_string())?;
+    serde_json::to_writer_pretty(buffer, watermarks)
+        .map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+}
+
+/// Where a bank's authorization is kept: its own `--auth` file, or an
+/// alias in the banks store.
+enum BankSource {
+    File(std::path::PathBuf),
+    Store {
+        path: std::path::PathBuf,
+        alias: String,
+    },
+}
+
+impl BankSource {
+    fn load(&self) -> Result<BankAuthState, String> {
+        match self {
+            BankSource::File(path) => parse_bank(path).map_err(|err| {
+                format!(
+                    "Unable to read bank state file at {}: {}",
+                    path.display(),
+                    err
+                )
+            }),
+            BankSource::Store { path, alias } => {
+                let store = parse_bank_store(path).map_err(|err| {
+                    format!(
+                        "Unable to read banks store at {}: {}",
+                        path.display(),
+                        err
+                    )
+                })?;
+                store.get(alias).cloned()
+            }
+        }
+    }
+
+    fn save(&self, state: &BankAuthState) -> Result<(), String> {
+        match self {
+            BankSource::File(path) => write_bank(state, path).map(|_| ()),
+            BankSource::Store { path, alias } => {
+                let mut store = parse_bank_store(path)?;
+                store.insert(alias, state.clone());
+                write_bank_store(&store, path).map_err(|err| {
+                    format!("Unable to write banks store to disk: {}", err)
+                })
+            }
+        }
+    }
+
+    fn is_stdio(&self) -> bool {
+        match self {
+            BankSource::File(path) => is_stdio(path),
+            BankSource::Store { path, .. } => is_stdio(path),
+        }
+    }
+
+    /// The option selecting this bank again, for hints.
+    fn option(&self) -> String {
+        match self {
+            BankSource::File(path) => format!("--auth {}", path.display()),
+            BankSource::Store { alias, .. } => format!("--bank {}", alias),
+        }
+    }
+}
+
+fn load_bank_or_exit(bank: &BankSource) -> BankAuthState {
+    bank.load().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
+}
+
+fn parse_contacts(path: &std::path::PathBuf) -> Result<Contacts, String> {
+    let contents = match read_file(path) {
         Err(err) => {
-            return Err(format!("Unable to write bank state to disk: {}", err));
+            return Err(format!("Error reading contacts file: {}", err));
         }
-        Ok(_) => {}
+        Ok(val) => val,
     };
-    Ok(auth)
+
+    let contacts: Contacts = match serde_json::from_str(&contents) {
+        Err(err) => {
+            return Err(format!(
+                "Unable to parse contacts file at {}: {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(value) => value,
+    };
+    Ok(contacts)
+}
+
+fn write_contacts(
+    contacts: &Contacts,
+    path: &std::path::PathBuf,
+) -> Result<(), String> {
+    let buffer = match create_file(path) {
+        Err(err) => {
+            return Err(format!(
+                "Unable to open contacts file for writing: {}",
+                err
+            ));
+        }
+        Ok(res) => res,
+    };
+
+    if let Err(err) = serde_json::to_writer_pretty(buffer, contacts) {
+        return Err(format!("Unable to write contacts to disk: {}", err));
+    }
+    Ok(())
 }
 
 fn get_state(path: &std::path::PathBuf) -> Result<NordigenState, ErrorKind> {
-    if !path.exists() {
+    if !is_stdio(path) && !path.exists() {
         return Err(ErrorKind::NotFound);
     }
 
-    match parse_state(&path) {
+    match parse_state(path) {
         Err(error) => {
             eprintln!("Error obtaining on-disk state: {}", error);
-            return Err(ErrorKind::InvalidData);
+            Err(ErrorKind::InvalidData)
         }
-        Ok(res) => {
-            return Ok(res);
-        }
-    };
+        Ok(res) => Ok(res),
+    }
 }
 
 fn print_state_error(err: ErrorKind) {
     match err {
         ErrorKind::NotFound => {
-            eprintln!("State file not found");
+            eprintln!("{}", tr!(StateNotFound));
         }
         ErrorKind::InvalidData => {
-            eprintln!("Invalid state file found");
+            eprintln!("{}", tr!(StateInvalid));
         }
         _ => {
-            eprintln!("Unknown error!");
+            eprintln!("{}", tr!(UnknownError));
         }
     }
 }
 
 fn get_state_or_exit(path: &std::path::PathBuf) -> NordigenState {
-    match get_state(&path) {
+    match get_state(path) {
         Err(error) => {
             print_state_error(error);
             std::process::exit(1);
         }
-        Ok(res) => return res,
-    };
+        Ok(res) => res,
+    }
 }
 
-async fn do_authorize(cmd: &AuthorizeCmd) {
-    println!("authorize client");
-
-    if cmd.state.exists() {
-        println!("Found on-disk state...");
-        let state = parse_state(&cmd.state).unwrap_or_else(|err| {
-            eprintln!("Error obtaining on-disk state: {}", err);
-            std::process::exit(1);
-        });
+/// Where bank commands obtain their access token from.
+enum StateSource {
+    /// State file written by `authorize` and `refresh`.
+    File(std::path::PathBuf),
+    /// Authorize anew from the secrets in this config file, keeping the
+    /// resulting tokens in memory only.
+    Ephemeral(std::path::PathBuf),
+}
 
-        if state.is_refresh_expired() {
-            eprintln!("Refresh token has expired!");
-        } else if state.is_token_expired() {
-            println!("Access token expired. Please refresh!");
-            std::process::exit(0);
-        } else {
-            println!("Authorization still valid");
-            std::process::exit(0);
+async fn authorize_in_memory(
+    configpath: &std::path::PathBuf,
+) -> Result<NordigenState, String> {
+    let config = match parse_config(configpath) {
+        Err(err) => {
+            return Err(format!("Error parsing config: {}", err));
         }
-    }
-
-    println!("Obtaining new authorization...");
-
-    let config = parse_config(&cmd.config).unwrap_or_else(|err| {
-        println!("Error parsing config: {err}");
-        std::process::exit(1);
-    });
-    println!("config: {}", config);
-    let authorization =
-        authorize::authorize(&config).await.unwrap_or_else(|err| {
-            println!("Error obtaining authorization: {err}");
-            std::process::exit(1);
-        });
+        Ok(res) => res,
+    };
+    let authorization = match authorize::authorize(&config).await {
+        Err(err) => {
+            return Err(format!("Error obtaining authorization: {}", err));
+        }
+        Ok(res) => res,
+    };
 
-    let state = write_state(
-        &cmd.state,
+    Ok(NordigenState::new(
         authorization.access,
-        authorization.refresh,
         authorization.access_expires,
+        authorization.refresh,
         authorization.refresh_expires,
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("Unable to write state: {err}");
-        std::process::exit(1);
-    });
+    ))
+}
 
-    let access_expires = state.token_expires_on().to_string();
-    println!(
-        "Obtained authorization token; expires on {}",
-        access_expires
-    );
+async fn obtain_state_or_exit(source: &StateSource) -> NordigenState {
+    match source {
+        StateSource::File(path) => get_state_or_exit(path),
+        StateSource::Ephemeral(configpath) => {
+            authorize_in_memory(configpath).await.unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            })
+        }
+    }
 }
 
-async fn do_refresh(cmd: &RefreshCmd) {
-    println!("refresh authorization");
-    let state = get_state_or_exit(&cmd.state);
-    if !state.is_token_expired() {
-        println!("Token is still valid and does not need to be refreshed.");
-        std::process::exit(0);
-    } else if state.is_refresh_expired() {
-        eprintln!("Refresh token has expired. Please authorize again.");
+/// Stdin can only be read once, so at most one input may be `-`.
+fn check_stdin_or_exit(paths: &[&Option<std::path::PathBuf>]) {
+    let readers = paths
+        .iter()
+        .filter(|p| matches!(p, Some(path) if is_stdio(path)))
+        .count();
+    if readers > 1 {
+        eprintln!("Only one file may be read from stdin.");
         std::process::exit(1);
     }
+}
 
-    let (new_token, new_expires) = authorize::refresh(&state.refresh_token)
-        .await
-        .unwrap_or_else(|err| {
-            eprintln!("Error refreshing token: {}", err);
-            std::process::exit(1);
-        });
+/// Where to keep a refreshed token; refreshed tokens are only kept in
+/// memory when read-only.
+fn refreshed_state_path(source: &StateSource) -> Option<std::path::PathBuf> {
+    match source {
+        StateSource::File(path)
+            if !is_stdio(path) && !guard::is_read_only() =>
+        {
+            Some(path.clone())
+        }
+        _ => None,
+    }
+}
 
-    let new_state = write_state(
-        &cmd.state,
-        new_token,
-        state.refresh_token,
-        new_expires,
-        state.refresh_expires,
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("Unable to write state: {}", err);
+/// A valid access token, refreshed first if it has expired. State read
+/// from a file is updated in place, unless it came from stdin.
+async fn get_token_or_exit(source: &StateSource) -> String {
+    let state = obtain_state_or_exit(source).await;
+    let mut session = SessionManager::new(state, refreshed_state_path(source));
+    session.token().await.unwrap_or_else(|err| {
+        eprintln!("{}", err);
         std::process::exit(1);
-    });
-
-    let access_expires = new_state.token_expires_on().to_string();
-    println!(
-        "Successfully refreshed; new token expires on {}",
-        access_expires
-    );
+    })
 }
 
-async fn do_bank_list(cmd: &BankListCmd, statepath: &std::path::PathBuf) {
-    let state = get_state_or_exit(&statepath);
-    if state.is_token_expired() {
-        eprintln!("Token has expired. Maybe refresh?");
-        std::process::exit(1);
-    }
-    let banks = match banks::list(&state.token, &cmd.country).await {
-        Err(error) => {
-            eprintln!("Error obtaining bank list: {}", error);
-            std::process::exit(1);
+/// As `get_token_or_exit`, returning rather than exiting on failure, for
+/// going through several profiles.
+async fn get_token(source: &StateSource) -> Result<String, String> {
+    let state = match source {
+        StateSource::File(path) => {
+            get_state(path).map_err(|err| match err {
+                ErrorKind::NotFound => String::from("not authorized"),
+                _ => String::from("invalid state"),
+            })?
+        }
+        StateSource::Ephemeral(configpath) => {
+            authorize_in_memory(configpath).await?
         }
-        Ok(res) => res,
     };
+    let mut session = SessionManager::new(state, refreshed_state_path(source));
+    session.token().await
+}
 
-    let mut table = Table::new();
-    table.add_row(Row::new(vec![
-        Cell::new("Country").with_style(Attr::Bold),
-        Cell::new("ID").with_style(Attr::Bold),
-        Cell::new("Name").with_style(Attr::Bold),
-        Cell::new("Tx Days").with_style(Attr::Bold),
-    ]));
+fn resolve_or_exit(
+    path: &Option<std::path::PathBuf>,
+    dir: &Option<std::path::PathBuf>,
+    name: &str,
+    option: &str,
+) -> std::path::PathBuf {
+    paths::resolve(path, dir, name).unwrap_or_else(|| {
+        eprintln!(
+            "Please specify {} or --config-dir; HOME is not set.",
+            option
+        );
+        std::process::exit(1);
+    })
+}
 
-    for bank in &banks {
-        let country_str = bank.countries.join(", ");
-        table.add_row(row![
-            country_str,
-            bank.id,
-            bank.name,
-            bank.transaction_total_days
-        ]);
+/// The one account matching `select`, shared by all commands acting on a
+/// single account; exits if there's no match, or several.
+fn select_account<'a>(
+    accounts: &'a [banks::Account],
+    select: &AccountSelectArgs,
+) -> &'a banks::Account {
+    let (mut matches, mut what): (Vec<&banks::Account>, String) =
+        if let Some(id) = &select.account_id {
+            (
+                accounts.iter().filter(|e| &e.meta.id == id).collect(),
+                format!("account id {}", id),
+            )
+        } else if let Some(iban) = &select.iban {
+            (
+                accounts.iter().filter(|e| e.has_iban(iban)).collect(),
+                format!("IBAN {}", iban),
+            )
+        } else if let Some(pan) = &select.pan {
+            (
+                accounts.iter().filter(|e| e.has_pan_suffix(pan)).collect(),
+                format!("masked PAN {}", pan),
+            )
+        } else if let Some(name) = &select.name {
+            (
+                accounts.iter().filter(|e| e.has_name(name)).collect(),
+                format!("name {}", name),
+            )
+        } else {
+            (vec![], String::from("no selector"))
+        };
+
+    if let Some(currency) = &select.currency {
+        matches.retain(|e| e.has_currency(currency));
+        what = format!("{} and currency {}", what, currency);
     }
-    table.printstd();
-}
 
-async fn do_bank_authorization(
-    cmd: &BankAuthorizeCmd,
-    statepath: &std::path::PathBuf,
-) {
-    let state = get_state_or_exit(statepath);
-    if state.is_token_expired() {
-        eprintln!("Token has expired. Maybe refresh?");
+    if matches.len() > 1 {
+        eprintln!("{}", tr!(AccountAmbiguous, what));
+        for entry in &matches {
+            eprintln!(
+                "  {}  {}  {}",
+                entry.meta.id,
+                entry.details.currency.as_deref().unwrap_or("???"),
+                entry.details.name.as_deref().unwrap_or("")
+            );
+        }
+        eprintln!("{}", tr!(AccountDisambiguate));
         std::process::exit(1);
     }
 
-    let mut auth = banks::Authorize::new(&state.token, &cmd.bank_id);
-    let link = auth.start().await.unwrap_or_else(|err| {
-        eprintln!("Error starting authorization: {}", err);
-        std::process::exit(1);
+    match matches.first().copied() {
+        None => {
+            eprintln!("{}", tr!(AccountNotFound, what));
+            std::process::exit(1);
+        }
+        Some(res) => res,
+    }
+}
+
+/// The accounts of `bankstate`'s requisition, as cached in it unless
+/// `refresh` is given. Should access to them have expired, offers to
+/// authorize the bank again, replacing what `bank` holds.
+async fn get_accounts_or_exit(
+    token: &str,
+    bankstate: &BankAuthState,
+    bank: &BankSource,
+    refresh: bool,
+) -> Vec<banks::Account> {
+    // past the agreement's expiry, the cached accounts would only fail
+    // later, when fetching their data
+    let expired =
+        matches!(bankstate.expires, Some(at) if at <= chrono::Utc::now());
+    if !refresh && !expired && !bankstate.accounts.is_empty() {
+        tracing::debug!(
+            "using {} cached accounts of {}",
+            bankstate.accounts.len(),
+            bankstate.bank_id
+        );
+        return bankstate.accounts.clone();
+    }
+
+    let requisition_id = &bankstate.requisition.requisition_id;
+    let err = match banks::accounts(token, requisition_id).await {
+        Ok(res) => return cache_accounts(bankstate, bank, res),
+        Err(err) => err,
+    };
+    eprintln!("Error obtaining accounts metadata: {}", err);
+    if !err.is_access_expired() {
+        std::process::exit(err.exit_code());
+    }
+
+    let bankstate = reauthorize(token, bankstate, bank).await;
+    let accounts =
+        banks::accounts(token, &bankstate.requisition.requisition_id)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("Error obtaining accounts metadata: {}", err);
+                std::process::exit(err.exit_code());
+            });
+    cache_accounts(&bankstate, bank, accounts)
+}
+
+/// Keep `accounts` in `bankstate`, as held by `bank`, for later commands;
+/// failing to is only worth a warning.
+fn cache_accounts(
+    bankstate: &BankAuthState,
+    bank: &BankSource,
+    accounts: Vec<banks::Account>,
+) -> Vec<banks::Account> {
+    if bank.is_stdio() || guard::is_read_only() {
+        return accounts;
+    }
+    let mut bankstate = bankstate.clone();
+    bankstate.accounts = accounts.clone();
+    if let Err(err) = bank.save(&bankstate) {
+        eprintln!("Unable to cache accounts metadata: {}", err);
+    }
+    accounts
+}
+
+/// Run `bank authorize` again for `bankstate`'s bank, with Nordigen's
+/// default terms, if running interactively and the user agrees; exits
+/// with a hint otherwise.
+async fn reauthorize(
+    token: &str,
+    bankstate: &BankAuthState,
+    bank: &BankSource,
+) -> BankAuthState {
+    let bank_id = &bankstate.bank_id;
+    let interactive = std::io::stdin().is_terminal()
+        && !bank.is_stdio()
+        && !bank_id.is_empty()
+        && !guard::is_read_only();
+    if !interactive {
+        let bank_hint = if bank_id.is_empty() {
+            "<BANK-ID>"
+        } else {
+            bank_id
+        };
+        eprintln!(
+            "Access has expired; authorize again with `nordigen-cli bank \
+             authorize {} {}`.",
+            bank_hint,
+            bank.option()
+        );
+        std::process::exit(1);
+    }
+
+    eprint!(
+        "Access to {} has expired. Authorize it again now? [y/N] ",
+        bank_id
+    );
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err()
+        || !answer.trim().eq_ignore_ascii_case("y")
+    {
+        std::process::exit(1);
+    }
+
+    let (host, port) = redirect_address(bankstate);
+    let listener = banks::CallbackListener::bind(&host, port)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(err.exit_code());
+        });
+    let flow = banks::AuthFlow::start(token, bank_id, None, &listener)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("{}: {}", bank_id, err);
+            std::process::exit(err.exit_code());
+        });
+    info!("{}", tr!(FollowLink));
+    eprintln!("{}", flow.requisition.link);
+
+    let progress = Progress::start("authorize banks");
+    let bankstate = finish_bank_authorization(token, flow, bank)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("{}: {}", bank_id, err);
+            std::process::exit(1);
+        });
+    progress.finish(true);
+    eprintln!("{}", tr!(BankAuthorized));
+    bankstate
+}
+
+/// The host and port the bank was last authorized to redirect to, so that
+/// authorizing it again listens where that did; the defaults otherwise.
+fn redirect_address(bankstate: &BankAuthState) -> (String, u16) {
+    let redirect = bankstate
+        .redirect
+        .as_deref()
+        .and_then(|url| url::Url::parse(url).ok());
+    let host = redirect.as_ref().and_then(|url| url.host_str());
+    match host {
+        None => (banks::DEFAULT_HOST.to_string(), banks::DEFAULT_PORT),
+        Some(host) => {
+            let port = redirect
+                .as_ref()
+                .and_then(|url| url.port_or_known_default())
+                .unwrap_or(banks::DEFAULT_PORT);
+            (host.to_string(), port)
+        }
+    }
+}
+
+async fn do_authorize(
+    configpath: &std::path::PathBuf,
+    statepath: &std::path::PathBuf,
+) {
+    tracing::debug!("authorizing client, state at {}", statepath.display());
+
+    if !is_stdio(statepath) && statepath.exists() {
+        tracing::debug!("found on-disk state");
+        let state = parse_state(statepath).unwrap_or_else(|err| {
+            eprintln!("Error obtaining on-disk state: {}", err);
+            std::process::exit(1);
+        });
+
+        if state.is_refresh_expired() {
+            eprintln!("{}", tr!(RefreshExpired));
+        } else if state.is_token_expired() {
+            eprintln!("{}", tr!(AccessExpiredRefresh));
+            std::process::exit(0);
+        } else {
+            eprintln!("{}", tr!(AuthorizationValid));
+            std::process::exit(0);
+        }
+    }
+
+    guard::deny_or_exit("write the state file");
+    tracing::info!("obtaining new authorization");
+
+    let config = parse_config(configpath).unwrap_or_else(|err| {
+        eprintln!("Error parsing config: {err}");
+        std::process::exit(1);
+    });
+    let authorization =
+        authorize::authorize(&config).await.unwrap_or_else(|err| {
+            eprintln!("Error obtaining authorization: {err}");
+            std::process::exit(1);
+        });
+
+    let state = write_state(
+        statepath,
+        authorization.access,
+        authorization.refresh,
+        authorization.access_expires,
+        authorization.refresh_expires,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Unable to write state: {err}");
+        std::process::exit(1);
+    });
+
+    let access_expires = state.token_expires_on().to_string();
+    eprintln!("{}", tr!(ObtainedToken, access_expires));
+}
+
+async fn do_authorize_ephemeral(configpath: &std::path::PathBuf) {
+    tracing::debug!("authorizing client, in memory");
+
+    let state = authorize_in_memory(configpath).await.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let access_expires = state.token_expires_on().to_string();
+    eprintln!("{}", tr!(EphemeralToken, access_expires));
+}
+
+async fn do_refresh(statepath: &std::path::PathBuf) {
+    tracing::debug!(
+        "refreshing authorization, state at {}",
+        statepath.display()
+    );
+    let state = get_state_or_exit(statepath);
+    let mut session = SessionManager::new(state, Some(statepath.clone()));
+    if !session.expires_soon() {
+        eprintln!("{}", tr!(TokenStillValid));
+        std::process::exit(0);
+    }
+    guard::deny_or_exit("write the state file");
+
+    session.refresh().await.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let access_expires = session.state().token_expires_on().to_string();
+    eprintln!("{}", tr!(RefreshedToken, access_expires));
+}
+
+#[derive(serde::Serialize)]
+struct Secrets<'a> {
+    secret_id: &'a str,
+    secret_key: &'a str,
+}
+
+/// Replace the top-level `secret_id` and `secret_key` of a config file's
+/// contents, leaving comments and other settings alone.
+fn replace_secrets(contents: &str, secrets: &Secrets) -> String {
+    let id_line =
+        format!("secret_id = {}", toml::Value::from(secrets.secret_id));
+    let key_line =
+        format!("secret_key = {}", toml::Value::from(secrets.secret_key));
+
+    let mut out: Vec<String> = vec![];
+    let mut top_level = true;
+    let (mut has_id, mut has_key) = (false, false);
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            top_level = false;
+        }
+        let key = trimmed.split('=').next().unwrap_or("").trim();
+        if top_level && key == "secret_id" {
+            out.push(id_line.clone());
+            has_id = true;
+        } else if top_level && key == "secret_key" {
+            out.push(key_line.clone());
+            has_key = true;
+        } else {
+            out.push(line.to_string());
+        }
+    }
+
+    if !has_key {
+        out.insert(0, key_line);
+    }
+    if !has_id {
+        out.insert(0, id_line);
+    }
+    out.join("\n") + "\n"
+}
+
+/// Write `contents` next to `path` and rename it into place, so readers
+/// never observe a partially written file. The temporary file is private
+/// to us from the start, keeping the mode of the file it replaces.
+fn write_atomically(
+    path: &std::path::PathBuf,
+    contents: &str,
+) -> Result<(), String> {
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = std::path::PathBuf::from(tmp);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mode = std::fs::metadata(path)
+            .map(|meta| meta.permissions().mode() & 0o777)
+            .unwrap_or(0o600);
+        options.mode(mode);
+    }
+    let res = options.open(&tmp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(err) = res {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(format!("Unable to write {}: {}", tmp.display(), err));
+    }
+    if let Err(err) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(format!("Unable to replace {}: {}", path.display(), err));
+    }
+    Ok(())
+}
+
+/// `key` if given, otherwise a line read from stdin, prompting for it on
+/// a terminal.
+fn read_secret_key_or_exit(key: &Option<String>) -> String {
+    let secret_key = match key {
+        Some(key) => key.clone(),
+        None => {
+            if std::io::stdin().is_terminal() {
+                eprint!("Secret key: ");
+            }
+            let mut key = String::new();
+            if let Err(err) = std::io::stdin().read_line(&mut key) {
+                eprintln!("Error reading secret key from stdin: {}", err);
+                std::process::exit(1);
+            }
+            key.trim().to_string()
+        }
+    };
+    if secret_key.is_empty() {
+        eprintln!("No secret key given.");
+        std::process::exit(1);
+    }
+    secret_key
+}
+
+fn secrets_config_or_exit(secrets: &Secrets) -> NordigenConfig {
+    toml::to_string(secrets)
+        .ok()
+        .and_then(|val| toml::from_str(&val).ok())
+        .unwrap_or_else(|| {
+            eprintln!("Unable to build config from the secrets.");
+            std::process::exit(1);
+        })
+}
+
+async fn do_secrets_store(cmd: &SecretsStoreCmd) {
+    guard::deny_or_exit("store secrets");
+    let secret_key = read_secret_key_or_exit(&cmd.secret_key);
+    let secrets = Secrets {
+        secret_id: &cmd.secret_id,
+        secret_key: &secret_key,
+    };
+
+    info!("Checking the secrets...");
+    let config = secrets_config_or_exit(&secrets);
+    if let Err(err) = authorize::authorize(&config).await {
+        eprintln!("Secrets were rejected: {}", err);
+        std::process::exit(1);
+    }
+
+    keychain::store(&cmd.secret_id, &secret_key).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    info!("Stored the secrets in the keyring.");
+}
+
+fn do_secrets_delete() {
+    guard::deny_or_exit("delete secrets");
+    match keychain::delete() {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok(false) => info!("No secrets in the keyring."),
+        Ok(true) => info!("Deleted the secrets from the keyring."),
+    }
+}
+
+fn write_config_or_exit(configpath: &std::path::PathBuf, secrets: &Secrets) {
+    let contents = toml::to_string(secrets).unwrap_or_else(|err| {
+        eprintln!("Unable to write secrets: {}", err);
+        std::process::exit(1);
+    });
+    let mut out = create_private_file(configpath).unwrap_or_else(|err| {
+        eprintln!("Error creating config: {}", err);
+        std::process::exit(1);
+    });
+    if let Err(err) = out.write_all(contents.as_bytes()) {
+        eprintln!("Error writing config: {}", err);
+        std::process::exit(1);
+    }
+    info!("Wrote config to {}", configpath.display());
+}
+
+async fn do_init(
+    cmd: &InitCmd,
+    configpath: &std::path::PathBuf,
+    statepath: &Option<std::path::PathBuf>,
+) {
+    guard::deny_or_exit("write a config or state file");
+    // With secrets in the keyring, new ones replace them there; in the
+    // config file, they'd take precedence over the keyring's for good.
+    let in_keyring = keychain::has_secrets();
+    if !in_keyring {
+        if is_stdio(configpath) {
+            eprintln!("The config can only be written to a file.");
+            std::process::exit(1);
+        }
+        if configpath.exists() && !cmd.force {
+            eprintln!(
+                "Config file already exists at {}; use --force to replace it.",
+                configpath.display()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let secret_key = read_secret_key_or_exit(&cmd.secret_key);
+    let secrets = Secrets {
+        secret_id: &cmd.secret_id,
+        secret_key: &secret_key,
+    };
+
+    info!("Checking the secrets...");
+    let config = secrets_config_or_exit(&secrets);
+    let authorization =
+        authorize::authorize(&config).await.unwrap_or_else(|err| {
+            eprintln!("Secrets were rejected: {}", err);
+            std::process::exit(1);
+        });
+
+    if in_keyring {
+        keychain::store(&cmd.secret_id, &secret_key).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        info!("Stored the secrets in the keyring.");
+    } else {
+        write_config_or_exit(configpath, &secrets);
+    }
+
+    if let Some(path) = statepath {
+        let state = write_state(
+            path,
+            authorization.access,
+            authorization.refresh,
+            authorization.access_expires,
+            authorization.refresh_expires,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to write state: {}", err);
+            std::process::exit(1);
+        });
+        let access_expires = state.token_expires_on().to_string();
+        eprintln!("{}", tr!(ObtainedToken, access_expires));
+    }
+}
+
+async fn do_rotate_secrets(
+    cmd: &RotateSecretsCmd,
+    configpath: &std::path::PathBuf,
+    statepath: &Option<std::path::PathBuf>,
+) {
+    guard::deny_or_exit("rotate secrets");
+    // Rotated where they are kept: secrets in the keyring are replaced
+    // there, leaving the config file alone.
+    let in_keyring = keychain::has_secrets();
+    if !in_keyring && is_stdio(configpath) {
+        eprintln!("Secrets can only be rotated in a config file.");
+        std::process::exit(1);
+    }
+    let contents = if in_keyring {
+        None
+    } else {
+        Some(read_file(configpath).unwrap_or_else(|err| {
+            eprintln!("Error reading config: {}", err);
+            std::process::exit(1);
+        }))
+    };
+
+    let secret_key = read_secret_key_or_exit(&cmd.secret_key);
+    let secrets = Secrets {
+        secret_id: &cmd.secret_id,
+        secret_key: &secret_key,
+    };
+
+    info!("Checking the new secrets...");
+    let config = secrets_config_or_exit(&secrets);
+    let authorization =
+        authorize::authorize(&config).await.unwrap_or_else(|err| {
+            eprintln!("New secrets were rejected: {}", err);
+            std::process::exit(1);
+        });
+
+    match contents {
+        None => {
+            keychain::store(&cmd.secret_id, &secret_key).unwrap_or_else(
+                |err| {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                },
+            );
+            info!("Updated secrets in the keyring.");
+        }
+        Some(contents) => {
+            write_atomically(configpath, &replace_secrets(&contents, &secrets))
+                .unwrap_or_else(|err| {
+                    eprintln!("Error updating config: {}", err);
+                    std::process::exit(1);
+                });
+            info!("Updated secrets in {}", configpath.display());
+        }
+    }
+
+    // Tokens obtained with the old secrets are no longer ours to refresh.
+    if let Some(path) = statepath {
+        let state = write_state(
+            path,
+            authorization.access,
+            authorization.refresh,
+            authorization.access_expires,
+            authorization.refresh_expires,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to write state: {}", err);
+            std::process::exit(1);
+        });
+        let access_expires = state.token_expires_on().to_string();
+        eprintln!("{}", tr!(ObtainedToken, access_expires));
+    }
+}
+
+/// Print what changed in `banks` since `previous` was fetched, keeping
+/// only the changes to banks `keep` agrees with.
+fn print_bank_list_diff(
+    previous: &catalog::Listing,
+    banks: &[banks::Institution],
+    keep: impl Fn(&banks::Institution) -> bool,
+) {
+    let changes: Vec<catalog::Change> =
+        catalog::diff(&previous.institutions, banks)
+            .into_iter()
+            .filter(|change| keep(change.institution()))
+            .collect();
+    let since = previous.fetched.format("%Y-%m-%d %H:%M");
+    if changes.is_empty() {
+        info!("No changes since {}", since);
+        return;
+    }
+    info!("{} changes since {}", changes.len(), since);
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Change").with_style(Attr::Bold),
+        Cell::new("ID").with_style(Attr::Bold),
+        Cell::new("Name").with_style(Attr::Bold),
+        Cell::new("Details").with_style(Attr::Bold),
+    ]));
+    for change in &changes {
+        let (kind, details) = match change {
+            catalog::Change::Added(_) => ("added", String::new()),
+            catalog::Change::Removed(_) => ("removed", String::new()),
+            catalog::Change::Changed(_, fields) => {
+                ("changed", fields.join("\n"))
+            }
+        };
+        let bank = change.institution();
+        table.add_row(row![kind, bank.id, bank.name, details]);
+    }
+    table.printstd();
+}
+
+async fn do_bank_list(
+    cmd: &BankListCmd,
+    source: &StateSource,
+    catalog: &Option<std::path::PathBuf>,
+) {
+    let token = get_token_or_exit(source).await;
+    let mut banks = match banks::list(&token, &cmd.country).await {
+        Err(error) => {
+            eprintln!("Error obtaining bank list: {}", error);
+            std::process::exit(error.exit_code());
+        }
+        Ok(res) => res,
+    };
+
+    let keep = |bank: &banks::Institution| {
+        let matches = match &cmd.search {
+            None => true,
+            Some(text) => bank.matches(text),
+        };
+        let has_prefix = match &cmd.id {
+            None => true,
+            Some(prefix) => {
+                bank.id.to_uppercase().starts_with(&prefix.to_uppercase())
+            }
+        };
+        matches && has_prefix
+    };
+
+    // Every listing is kept, so that a later --diff has something to
+    // compare with.
+    if let Some(path) = catalog {
+        let mut stored = parse_catalog(path).unwrap_or_else(|err| {
+            eprintln!("Unable to read the last bank list: {}", err);
+            std::process::exit(1);
+        });
+        if cmd.diff {
+            match stored.get(&cmd.country) {
+                None => info!(
+                    "No earlier bank list to compare with; keeping this one"
+                ),
+                Some(previous) => print_bank_list_diff(previous, &banks, keep),
+            }
+        }
+        if !guard::is_read_only() {
+            stored.set(&cmd.country, banks.clone());
+            if let Err(err) = write_catalog(&stored, path) {
+                eprintln!("Unable to keep the bank list: {}", err);
+            }
+        }
+        if cmd.diff {
+            return;
+        }
+    } else if cmd.diff {
+        eprintln!("No data directory to keep bank lists in; use --config-dir");
+        std::process::exit(1);
+    }
+
+    banks.retain(|bank| keep(bank));
+    match cmd.sort.as_deref() {
+        Some("name") => banks.sort_by_key(|bank| bank.name.to_lowercase()),
+        Some("country") => banks.sort_by(|a, b| a.countries.cmp(&b.countries)),
+        Some("days") => {
+            banks.sort_by_key(|bank| std::cmp::Reverse(bank.total_days()))
+        }
+        _ => {}
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Country").with_style(Attr::Bold),
+        Cell::new("ID").with_style(Attr::Bold),
+        Cell::new("Name").with_style(Attr::Bold),
+        Cell::new("Tx Days").with_style(Attr::Bold),
+    ]));
+
+    for bank in &banks {
+        let country_str = bank.countries.join(", ");
+        table.add_row(row![
+            country_str,
+            bank.id,
+            bank.name,
+            bank.transaction_total_days
+        ]);
+    }
+    table.printstd();
+}
+
+#[derive(serde::Deserialize)]
+struct BatchEntry {
+    id: String,
+    auth: std::path::PathBuf,
+}
+
+/// Banks to authorize in sequence, as listed in a batch file.
+#[derive(serde::Deserialize)]
+struct Batch {
+    #[serde(rename = "bank", default)]
+    banks: Vec<BatchEntry>,
+}
+
+fn parse_batch(path: &std::path::PathBuf) -> Result<Batch, String> {
+    let contents = match read_file(path) {
+        Err(err) => {
+            return Err(format!("Error reading batch file: {}", err));
+        }
+        Ok(val) => val,
+    };
+    let batch: Batch = match toml::from_str(&contents) {
+        Ok(res) => res,
+        Err(error) => {
+            return Err(format!(
+                "Unable to parse batch file at {}: {}",
+                path.display(),
+                error
+            ));
+        }
+    };
+
+    Ok(batch)
+}
+
+async fn finish_bank_authorization(
+    token: &str,
+    flow: banks::AuthFlow,
+    bank: &BankSource,
+) -> Result<BankAuthState, String> {
+    let bank_state = match flow.finish(token).await {
+        Err(err) => return Err(err.to_string()),
+        Ok(res) => res,
+    };
+    if let Err(err) = bank.save(&bank_state) {
+        return Err(format!("Error writing bank state: {}", err));
+    }
+    Ok(bank_state)
+}
+
+async fn do_bank_authorization(
+    cmd: &BankAuthorizeCmd,
+    source: &StateSource,
+    bank: Option<BankSource>,
+) {
+    guard::deny_or_exit("create requisitions");
+    let token = get_token_or_exit(source).await;
+
+    let entries: Vec<(String, BankSource)> = match &cmd.batch {
+        Some(path) => parse_batch(path)
+            .unwrap_or_else(|err| {
+                eprintln!("Error parsing batch: {}", err);
+                std::process::exit(1);
+            })
+            .banks
+            .into_iter()
+            .map(|entry| (entry.id, BankSource::File(entry.auth)))
+            .collect(),
+        None => match (&cmd.bank_id, bank) {
+            (None, Some(bank)) if cmd.sandbox => {
+                vec![(banks::SANDBOX_ID.to_string(), bank)]
+            }
+            (Some(id), Some(bank)) => vec![(id.clone(), bank)],
+            _ => {
+                eprintln!("Please specify a bank id and auth file.");
+                std::process::exit(1);
+            }
+        },
+    };
+    let batched = entries.len() > 1;
+
+    let listener = if cmd.no_callback {
+        if cmd.redirect_port == 0 {
+            eprintln!(
+                "--redirect-port 0 needs a listener to pick the port; give \
+                 one explicitly with --no-callback."
+            );
+            std::process::exit(1);
+        }
+        None
+    } else {
+        Some(
+            banks::CallbackListener::bind(
+                &cmd.redirect_host,
+                cmd.redirect_port,
+            )
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(err.exit_code());
+            }),
+        )
+    };
+    let redirect =
+        format!("http://{}:{}/", cmd.redirect_host, cmd.redirect_port);
+
+    // Start every flow up front, so the banks may be gone through in any
+    // order; the listener routes each callback back to its own flow.
+    let mut failed = 0;
+    let mut institutions: Option<Vec<banks::Institution>> = None;
+    let mut pending = FuturesUnordered::new();
+    for (id, bank) in &entries {
+        let agreement = match new_agreement(id, &cmd.terms) {
+            None => None,
+            Some(new) => match banks::create_agreement(&token, &new).await {
+                Err(err) => {
+                    eprintln!("{}: Error creating agreement: {}", id, err);
+                    failed += 1;
+                    continue;
+                }
+                Ok(res) => Some(res.id),
+            },
+        };
+
+        let flow = match &listener {
+            Some(listener) => {
+                banks::AuthFlow::start(&token, id, agreement.clone(), listener)
+                    .await
+            }
+            None => banks::AuthFlow::start_headless(
+                &token,
+                id,
+                agreement.clone(),
+                &redirect,
+            )
+            .await
+            .map(|flow| {
+                let secs = cmd.timeout.saturating_mul(60);
+                flow.with_timeout(std::time::Duration::from_secs(secs))
+            }),
+        };
+        let flow = match flow {
+            Err(err) => {
+                discard_agreement(&token, id, agreement.as_deref()).await;
+                if institutions.is_none() {
+                    institutions = banks::list(&token, &None).await.ok();
+                }
+                match unknown_bank_hint(&institutions, id) {
+                    None => eprintln!("{}: {}", id, err),
+                    Some(hint) => eprintln!("{}", hint),
+                };
+                failed += 1;
+                continue;
+            }
+            Ok(res) => res,
+        };
+
+        if batched {
+            info!("Authorizing bank {}...", id);
+        }
+        info!("{}", tr!(FollowLink));
+        if bank.is_stdio() {
+            eprintln!("{}", flow.requisition.link);
+        } else {
+            println!("{}", flow.requisition.link);
+        }
+        if listener.is_none() {
+            info!(
+                "Waiting for requisition {} to be linked...",
+                flow.requisition.id
+            );
+        }
+
+        let token = &token;
+        pending.push(async move {
+            let res = finish_bank_authorization(token, flow, bank).await;
+            if res.is_err() {
+                discard_agreement(token, id, agreement.as_deref()).await;
+            }
+            (id, res)
+        });
+    }
+
+    let progress = Progress::start("authorize banks");
+    let total = pending.len();
+    let mut done = 0;
+    while let Some((id, res)) = pending.next().await {
+        done += 1;
+        progress.advance(done, total);
+        match res {
+            Err(err) => {
+                eprintln!("{}: {}", id, err);
+                failed += 1;
+            }
+            Ok(_) if batched => {
+                eprintln!("{}: {}", id, tr!(BankAuthorized));
+            }
+            Ok(_) => eprintln!("{}", tr!(BankAuthorized)),
+        };
+    }
+
+    progress.finish(failed == 0);
+
+    if failed > 0 {
+        eprintln!("Failed to authorize {} of {} banks", failed, entries.len());
+        std::process::exit(1);
+    }
+}
+
+/// Delete the agreement created for a flow that failed, rather than leave
+/// it behind unused; failing to is only worth a warning.
+async fn discard_agreement(
+    token: &str,
+    bank_id: &str,
+    agreement: Option<&str>,
+) {
+    let agreement_id = match agreement {
+        None => return,
+        Some(res) => res,
+    };
+    if let Err(err) = banks::delete_agreement(token, agreement_id).await {
+        eprintln!(
+            "{}: Error deleting agreement {}: {}",
+            bank_id, agreement_id, err
+        );
+    }
+}
+
+/// If `bank_id` is not a known institution, a message suggesting the
+/// closest ones.
+fn unknown_bank_hint(
+    institutions: &Option<Vec<banks::Institution>>,
+    bank_id: &str,
+) -> Option<String> {
+    let institutions = institutions.as_ref()?;
+    if institutions.iter().any(|e| e.id == bank_id) {
+        return None;
+    }
+
+    let closest = banks::closest(institutions, bank_id, 5);
+    if closest.is_empty() {
+        return Some(format!("Unknown bank '{}'.", bank_id));
+    }
+    let mut hint = format!("Unknown bank '{}'; did you mean:", bank_id);
+    for entry in closest {
+        hint.push_str(&format!("\n  {}  ({})", entry.id, entry.name));
+    }
+    Some(hint)
+}
+
+fn new_agreement(
+    bank_id: &str,
+    terms: &AgreementTermsArgs,
+) -> Option<banks::NewAgreement> {
+    if terms.max_historical_days.is_none()
+        && terms.access_valid_for_days.is_none()
+        && terms.access_scope.is_empty()
+    {
+        return None;
+    }
+
+    Some(banks::NewAgreement {
+        institution_id: bank_id.to_string(),
+        max_historical_days: terms.max_historical_days,
+        access_valid_for_days: terms.access_valid_for_days,
+        access_scope: if terms.access_scope.is_empty() {
+            None
+        } else {
+            Some(terms.access_scope.clone())
+        },
+    })
+}
+
+fn print_agreement(agreement: &banks::Agreement) {
+    let created = match agreement.created {
+        None => String::from("unknown"),
+        Some(val) => val.to_string(),
+    };
+    let accepted = match agreement.accepted {
+        None => String::from("no"),
+        Some(val) => val.to_string(),
+    };
+
+    println!("          id: {}", agreement.id);
+    println!("     bank id: {}", agreement.institution_id);
+    println!("history days: {}", agreement.max_historical_days);
+    println!(" access days: {}", agreement.access_valid_for_days);
+    println!("       scope: {}", agreement.access_scope.join(", "));
+    println!("     created: {}", created);
+    println!("    accepted: {}", accepted);
+}
+
+async fn do_bank_agreement_create(
+    cmd: &BankAgreementCreateCmd,
+    source: &StateSource,
+) {
+    guard::deny_or_exit("create agreements");
+    let token = get_token_or_exit(source).await;
+
+    let new = match new_agreement(&cmd.bank_id, &cmd.terms) {
+        None => banks::NewAgreement {
+            institution_id: cmd.bank_id.clone(),
+            max_historical_days: None,
+            access_valid_for_days: None,
+            access_scope: None,
+        },
+        Some(res) => res,
+    };
+    let agreement =
+        banks::create_agreement(&token, &new)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("Error creating agreement: {}", err);
+                std::process::exit(err.exit_code());
+            });
+    print_agreement(&agreement);
+}
+
+async fn do_bank_agreement_list(source: &StateSource) {
+    let token = get_token_or_exit(source).await;
+
+    let agreements = banks::agreements(&token).await.unwrap_or_else(|err| {
+        eprintln!("Error obtaining agreements: {}", err);
+        std::process::exit(err.exit_code());
+    });
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("ID").with_style(Attr::Bold),
+        Cell::new("Bank").with_style(Attr::Bold),
+        Cell::new("History").with_style(Attr::Bold),
+        Cell::new("Access").with_style(Attr::Bold),
+        Cell::new("Scope").with_style(Attr::Bold),
+        Cell::new("Accepted").with_style(Attr::Bold),
+    ]));
+
+    for agreement in &agreements {
+        let accepted = match agreement.accepted {
+            None => String::from("no"),
+            Some(val) => val.date_naive().to_string(),
+        };
+        table.add_row(row![
+            agreement.id,
+            agreement.institution_id,
+            r->agreement.max_historical_days,
+            r->agreement.access_valid_for_days,
+            agreement.access_scope.join(", "),
+            accepted
+        ]);
+    }
+    table.printstd();
+}
+
+async fn do_bank_agreement_show(
+    cmd: &BankAgreementShowCmd,
+    source: &StateSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let agreement = banks::agreement(&token, &cmd.agreement_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining agreement: {}", err);
+            std::process::exit(err.exit_code());
+        });
+    print_agreement(&agreement);
+}
+
+async fn do_bank_agreement_delete(
+    cmd: &BankAgreementDeleteCmd,
+    source: &StateSource,
+) {
+    guard::deny_or_exit("delete agreements");
+    let token = get_token_or_exit(source).await;
+
+    let mut failed = 0;
+    for agreement_id in &cmd.agreement_ids {
+        match banks::delete_agreement(&token, agreement_id).await {
+            Err(err) => {
+                eprintln!("Error deleting agreement {}: {}", agreement_id, err);
+                failed += 1;
+            }
+            Ok(_) => info!("Deleted agreement {}", agreement_id),
+        };
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+async fn do_bank_show(cmd: &BankShowCmd, source: &StateSource) {
+    let token = get_token_or_exit(source).await;
+
+    let bank = banks::institution(&token, &cmd.bank_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining bank details: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    if cmd.json {
+        let json = serde_json::to_string_pretty(&bank).unwrap_or_else(|err| {
+            eprintln!("Error serializing bank details: {}", err);
+            std::process::exit(1);
+        });
+        println!("{}", json);
+        return;
+    }
+
+    let or_unknown = |v: &Option<String>| match v {
+        None => String::from("unknown"),
+        Some(val) => val.clone(),
+    };
+    let or_none = |v: &[String]| match v.is_empty() {
+        true => String::from("none"),
+        false => v.join(", "),
+    };
+
+    let institution = &bank.institution;
+    println!("                  id: {}", institution.id);
+    println!("                name: {}", institution.name);
+    println!("                 bic: {}", or_unknown(&institution.bic));
+    println!("                logo: {}", or_unknown(&institution.logo));
+    println!("           countries: {}", or_none(&institution.countries));
+    println!(
+        "    transaction days: {}",
+        institution.transaction_total_days
+    );
+    println!(
+        "         access days: {}",
+        or_unknown(&bank.max_access_valid_for_days)
+    );
+    println!(
+        "            features: {}",
+        or_none(&bank.supported_features)
+    );
+    println!(
+        "identification codes: {}",
+        or_none(&bank.identification_codes)
+    );
+    if bank.supported_payments.is_empty() {
+        println!("    payment products: none");
+    }
+    for (kind, products) in &bank.supported_payments {
+        println!("{:>20}: {}", kind, products.join(", "));
+    }
+}
+
+async fn do_bank_capabilities(cmd: &BankCapabilitiesCmd, source: &StateSource) {
+    let token = get_token_or_exit(source).await;
+
+    let bank = banks::institution(&token, &cmd.bank_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining bank details: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    let yes_no = |v: bool| if v { "yes" } else { "no" };
+    let account_types = bank.account_types();
+    let access_days = match &bank.max_access_valid_for_days {
+        None => String::from("unknown"),
+        Some(val) => val.clone(),
+    };
+
+    println!("               id: {}", bank.institution.id);
+    println!("             name: {}", bank.institution.name);
+    println!(
+        " transaction days: {}",
+        bank.institution.transaction_total_days
+    );
+    println!("      access days: {}", access_days);
+    if account_types.is_empty() {
+        println!("    account types: unknown");
+    } else {
+        println!("    account types: {}", account_types.join(", "));
+    }
+    println!(
+        "     pending txns: {}",
+        yes_no(bank.has_feature("pending_transactions"))
+    );
+    println!(
+        "account selection: {}",
+        yes_no(bank.has_feature("account_selection"))
+    );
+    println!(
+        " ssn verification: {}",
+        yes_no(bank.has_feature("ssn_verification"))
+    );
+    println!(
+        "         payments: {}",
+        yes_no(bank.has_feature("submit_payment"))
+    );
+    for (kind, products) in &bank.supported_payments {
+        println!("{:>17}: {}", kind, products.join(", "));
+    }
+}
+
+async fn do_bank_requisition_link(
+    cmd: &BankRequisitionLinkCmd,
+    source: &StateSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let requisition = banks::requisition(&token, &cmd.requisition_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining requisition: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    if !requisition.is_pending() {
+        eprintln!("{}", tr!(RequisitionStatus, requisition.status));
+    }
+    info!("{}", tr!(FollowLink));
+    println!("{}", requisition.link);
+}
+
+fn print_account(account: &banks::Account) {
+    let meta = &account.meta;
+    let details = &account.details;
+
+    let created_at = match meta.created {
+        None => String::from("unknown"),
+        Some(val) => val.to_string(),
+    };
+    let accessed_at = match meta.last_accessed {
+        None => String::from("unknown"),
+        Some(val) => val.to_string(),
+    };
+    let currency = match &details.currency {
+        None => String::from("unknown"),
+        Some(val) => val.clone(),
+    };
+
+    println!();
+    println!("   account id: {}", meta.id);
+    if let Some(iban) = &meta.iban {
+        println!("         iban: {}", iban);
+    }
+    if let Some(pan) = &details.masked_pan {
+        println!("   masked pan: {}", pan);
+    }
+    println!("     currency: {}", currency);
+    println!("      bank id: {}", meta.institution_id);
+    if let Some(name) = &details.name {
+        println!(" account name: {}", name);
+    }
+    if let Some(name) = details.owner_name.as_ref().or(meta.owner_name.as_ref())
+    {
+        println!("        owner: {}", name);
+    }
+    if let Some(product) = &details.product {
+        println!("      product: {}", product);
+    }
+    if let Some(account_type) = &details.cash_account_type {
+        println!(" account type: {}", account_type);
+    }
+    println!("      created: {}", created_at);
+    println!("last accessed: {}", accessed_at);
+    println!()
+}
+
+async fn do_bank_requisition_list(source: &StateSource) {
+    let token = get_token_or_exit(source).await;
+
+    let requisitions =
+        banks::requisitions(&token).await.unwrap_or_else(|err| {
+            eprintln!("Error obtaining requisitions: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("ID").with_style(Attr::Bold),
+        Cell::new("Bank").with_style(Attr::Bold),
+        Cell::new("Status").with_style(Attr::Bold),
+        Cell::new("Created").with_style(Attr::Bold),
+        Cell::new("Accounts").with_style(Attr::Bold),
+    ]));
+
+    for requisition in &requisitions {
+        let created = match requisition.created {
+            None => String::from("unknown"),
+            Some(val) => val.date_naive().to_string(),
+        };
+        table.add_row(row![
+            requisition.id,
+            requisition.institution_id,
+            requisition.status,
+            created,
+            r->requisition.accounts.len()
+        ]);
+    }
+    table.printstd();
+}
+
+async fn do_bank_requisition_show(
+    cmd: &BankRequisitionShowCmd,
+    source: &StateSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let requisition = banks::requisition(&token, &cmd.requisition_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining requisition: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    let created = match requisition.created {
+        None => String::from("unknown"),
+        Some(val) => val.to_string(),
+    };
+    let unknown = String::from("unknown");
+
+    println!("       id: {}", requisition.id);
+    println!("  bank id: {}", requisition.institution_id);
+    println!("   status: {}", requisition.status);
+    println!("  created: {}", created);
+    println!(
+        "reference: {}",
+        requisition.reference.as_ref().unwrap_or(&unknown)
+    );
+    println!(
+        "agreement: {}",
+        requisition.agreement.as_ref().unwrap_or(&unknown)
+    );
+    println!(
+        " redirect: {}",
+        requisition.redirect.as_ref().unwrap_or(&unknown)
+    );
+    println!("     link: {}", requisition.link);
+    println!(" accounts: {}", requisition.accounts.join(", "));
+}
+
+/// What `bank status` finds out about an authorization.
+struct BankStatus {
+    requisition: banks::Requisition,
+    agreement_id: Option<String>,
+    expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl BankStatus {
+    fn is_expired(&self) -> bool {
+        matches!(self.expires, Some(at) if at <= chrono::Utc::now())
+    }
+
+    fn is_usable(&self) -> bool {
+        self.requisition.is_linked() && !self.is_expired()
+    }
+}
+
+async fn bank_status(
+    token: &str,
+    bankstate: &BankAuthState,
+) -> Result<BankStatus, NordigenError> {
+    let requisition_id = &bankstate.requisition.requisition_id;
+    let requisition = banks::requisition(token, requisition_id).await?;
+
+    // Recorded when authorizing, with no need to ask for the agreement;
+    // files written by earlier versions lack it.
+    let agreement_id = bankstate
+        .agreement_id
+        .clone()
+        .or_else(|| requisition.agreement.clone());
+    let expires = match (bankstate.expires, &agreement_id) {
+        (Some(at), _) => Some(at),
+        (None, None) => None,
+        (None, Some(id)) => match banks::agreement(token, id).await {
+            Err(err) if err.is_not_found() => None,
+            Err(err) => return Err(err),
+            Ok(res) => res.expires(),
+        },
+    };
+    Ok(BankStatus {
+        requisition,
+        agreement_id,
+        expires,
+    })
+}
+
+/// Exits with status 1 if the requisition is not linked or its agreement
+/// has expired, for scripts to check before fetching.
+async fn do_bank_status(source: &StateSource, bank: &BankSource) {
+    let bankstate = load_bank_or_exit(bank);
+    let token = get_token_or_exit(source).await;
+
+    let status = bank_status(&token, &bankstate).await.unwrap_or_else(|err| {
+        eprintln!("Error obtaining bank status: {}", err);
+        std::process::exit(err.exit_code());
+    });
+    let requisition = &status.requisition;
+    let now = chrono::Utc::now();
+
+    let unknown = String::from("unknown");
+    println!("    bank id: {}", requisition.institution_id);
+    println!(
+        "  bank name: {}",
+        bankstate.institution_name.as_ref().unwrap_or(&unknown)
+    );
+    println!("requisition: {}", requisition.id);
+    match requisition.created.or(bankstate.created) {
+        None => println!("    created: unknown"),
+        Some(at) => println!("    created: {}", at),
+    }
+    let redirect = bankstate
+        .redirect
+        .as_ref()
+        .or(requisition.redirect.as_ref());
+    println!("   redirect: {}", redirect.unwrap_or(&unknown));
+    println!(
+        "     status: {} ({})",
+        requisition.status,
+        requisition.status_description()
+    );
+    println!(
+        "  agreement: {}",
+        status.agreement_id.as_ref().unwrap_or(&unknown)
+    );
+    match status.expires {
+        None => println!("    expires: unknown"),
+        Some(at) if status.is_expired() => {
+            println!("    expires: {} (expired)", at)
+        }
+        Some(at) => {
+            println!("    expires: {} (in {} days)", at, (at - now).num_days())
+        }
+    }
+    println!("   accounts: {}", requisition.accounts.len());
+
+    let usable = status.is_usable();
+    println!("     usable: {}", if usable { "yes" } else { "no" });
+    if !usable {
+        std::process::exit(1);
+    }
+}
+
+/// The banks kept in a profile: those in its banks store, by alias, and
+/// its bank.json, if any.
+fn profile_banks(
+    dir: &std::path::Path,
+) -> Result<Vec<(String, BankAuthState)>, String> {
+    let mut res: Vec<(String, BankAuthState)> =
+        parse_bank_store(&dir.join(paths::BANKS_FILE))?
+            .iter()
+            .map(|(alias, state)| (alias.clone(), state.clone()))
+            .collect();
+    let bankpath = dir.join(paths::BANK_FILE);
+    if bankpath.exists() {
+        res.push((paths::BANK_FILE.to_string(), parse_bank(&bankpath)?));
+    }
+    Ok(res)
+}
+
+/// `bank status` for every bank of every profile, in one table; exits
+/// with 1 if any of them is not usable.
+async fn do_bank_status_all_profiles(ephemeral: bool) {
+    let names = profiles::list().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    if names.is_empty() {
+        eprintln!(
+            "No profiles; create one with `nordigen-cli profile create`."
+        );
+        std::process::exit(1);
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Profile", "Bank", "Bank ID", "Status", "Expires", "Usable"]
+            .iter()
+            .map(|h| Cell::new(h).with_style(Attr::Bold))
+            .collect(),
+    ));
+    let mut all_usable = true;
+    for name in &names {
+        let dir = profiles::dir(name).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        keychain::set_profile(name);
+        let banks = match profile_banks(&dir) {
+            Err(err) => {
+                table.add_row(row![name, "", "", err, "", "no"]);
+                all_usable = false;
+                continue;
+            }
+            Ok(res) if res.is_empty() => {
+                table.add_row(row![name, "", "", "no banks", "", ""]);
+                continue;
+            }
+            Ok(res) => res,
+        };
+        let source = if ephemeral {
+            StateSource::Ephemeral(dir.join(paths::CONFIG_FILE))
+        } else {
+            StateSource::File(dir.join(paths::STATE_FILE))
+        };
+        let token = match get_token(&source).await {
+            Err(err) => {
+                table.add_row(row![name, "", "", err, "", "no"]);
+                all_usable = false;
+                continue;
+            }
+            Ok(res) => res,
+        };
+
+        for (alias, bankstate) in banks {
+            let status = match bank_status(&token, &bankstate).await {
+                Err(err) => {
+                    let row =
+                        row![name, alias, bankstate.bank_id, err, "", "no"];
+                    table.add_row(row);
+                    all_usable = false;
+                    continue;
+                }
+                Ok(res) => res,
+            };
+            let expires = match status.expires {
+                None => String::from("unknown"),
+                Some(at) if status.is_expired() => format!("{} (expired)", at),
+                Some(at) => at.to_string(),
+            };
+            let usable = status.is_usable();
+            all_usable &= usable;
+            table.add_row(row![
+                name,
+                alias,
+                status.requisition.institution_id,
+                status.requisition.status_description(),
+                expires,
+                if usable { "yes" } else { "no" }
+            ]);
+        }
+    }
+    table.printstd();
+    if !all_usable {
+        std::process::exit(1);
+    }
+}
+
+async fn do_bank_requisition_delete(
+    cmd: &BankRequisitionDeleteCmd,
+    source: &StateSource,
+) {
+    guard::deny_or_exit("delete requisitions");
+    let token = get_token_or_exit(source).await;
+
+    let mut failed = 0;
+    for requisition_id in &cmd.requisition_ids {
+        match banks::delete_requisition(&token, requisition_id).await {
+            Err(err) => {
+                eprintln!(
+                    "Error deleting requisition {}: {}",
+                    requisition_id, err
+                );
+                failed += 1;
+            }
+            Ok(_) => info!("Deleted requisition {}", requisition_id),
+        };
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+async fn do_bank_requisition_accounts(
+    cmd: &BankRequisitionAccountsCmd,
+    source: &StateSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let requisition = banks::requisition(&token, &cmd.requisition_id)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining requisition: {}", err);
+            std::process::exit(err.exit_code());
+        });
+
+    info!(
+        "requisition {} for {} ({}), {} accounts",
+        requisition.id,
+        requisition.institution_id,
+        requisition.status,
+        requisition.accounts.len()
+    );
+    let accounts = banks::accounts_by_id(&token, &requisition.accounts)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Error obtaining accounts metadata: {}", err);
+            std::process::exit(err.exit_code());
+        });
+    for account in &accounts {
+        print_account(account);
+    }
+}
+
+async fn do_bank_account_list(
+    cmd: &BankAccountListCmd,
+    source: &StateSource,
+    bank: &BankSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let bankstate = load_bank_or_exit(bank);
+
+    let accounts =
+        get_accounts_or_exit(&token, &bankstate, bank, cmd.refresh_accounts)
+            .await;
+    for account in &accounts {
+        print_account(account);
+    }
+}
+
+/// Booked and pending transactions of one account, as shown.
+struct AccountTransactions<'a> {
+    account: &'a banks::Account,
+    booked: Vec<&'a banks::Transaction>,
+    pending: Vec<&'a banks::Transaction>,
+}
+
+/// Write transactions as csv, with an account column first when there's
+/// more than one account.
+fn write_transactions_csv(
+    out: Box<dyn std::io::Write>,
+    groups: &[AccountTransactions],
+    with_header: bool,
+    label: &dyn Fn(&banks::Transaction) -> String,
+) -> Result<(), String> {
+    let mut writer = csv::Writer::from_writer(out);
+    let iban = |account: &Option<banks::AccountReference>| -> String {
+        account
+            .as_ref()
+            .and_then(|a| a.iban.clone())
+            .unwrap_or_default()
+    };
+    let date = |date: Option<chrono::NaiveDate>| -> String {
+        date.map(|d| d.to_string()).unwrap_or_default()
+    };
+    let with_account = groups.len() > 1;
+
+    let mut header = vec![
+        "status",
+        "booking_date",
+        "value_date",
+        "amount",
+        "currency",
+        "counterparty",
+        "creditor_name",
+        "creditor_iban",
+        "debtor_name",
+        "debtor_iban",
+        "remittance_information",
+        "transaction_id",
+    ];
+    if with_account {
+        header.insert(0, "account");
+    }
+    if with_header {
+        if let Err(err) = writer.write_record(&header) {
+            return Err(format!("{}", err));
+        }
+    }
+
+    for group in groups {
+        let entries = group
+            .booked
+            .iter()
+            .map(|tx| ("booked", tx))
+            .chain(group.pending.iter().map(|tx| ("pending", tx)));
+        for (status, tx) in entries {
+            let mut record = vec![
+                status.to_string(),
+                date(tx.booking_date),
+                date(tx.value_date),
+                tx.transaction_amount.amount.clone(),
+                tx.transaction_amount.currency.clone(),
+                label(tx),
+                tx.creditor_name.clone().unwrap_or_default(),
+                iban(&tx.creditor_account),
+                tx.debtor_name.clone().unwrap_or_default(),
+                iban(&tx.debtor_account),
+                tx.info().unwrap_or_default(),
+                tx.transaction_id.clone().unwrap_or_default(),
+            ];
+            if with_account {
+                record.insert(0, group.account.identifier());
+            }
+            if let Err(err) = writer.write_record(&record) {
+                return Err(format!("{}", err));
+            }
+        }
+    }
+
+    match writer.flush() {
+        Err(err) => Err(format!("{}", err)),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Write transactions as ledger or beancount entries, pending ones flagged
+/// as such, and only if `with_pending`.
+fn write_transactions_ledger(
+    mut out: Box<dyn std::io::Write>,
+    dialect: ledger::Dialect,
+    groups: &[AccountTransactions],
+    with_pending: bool,
+    accounts: &settings::LedgerSettings,
+    label: &dyn Fn(&banks::Transaction) -> String,
+    category: &dyn Fn(&banks::Transaction) -> Option<String>,
+) -> Result<(), String> {
+    for group in groups {
+        let account = accounts.account(group.account);
+        let pending: &[&banks::Transaction] = match with_pending {
+            true => &group.pending,
+            false => &[],
+        };
+        let entries = group
+            .booked
+            .iter()
+            .map(|tx| (false, tx))
+            .chain(pending.iter().map(|tx| (true, tx)));
+        for (pending, tx) in entries {
+            let date = match tx.booking_date.or(tx.value_date) {
+                None => {
+                    eprintln!(
+                        "Skipping undated transaction {}",
+                        tx.transaction_id.as_deref().unwrap_or("<no id>")
+                    );
+                    continue;
+                }
+                Some(val) => val,
+            };
+            let payee = label(tx);
+            let entry = ledger::Entry {
+                date,
+                pending,
+                counter_account: accounts.counter_account(
+                    &payee,
+                    category(tx).as_deref(),
+                    tx.is_debit(),
+                ),
+                payee,
+                narration: tx.info(),
+                amount: tx.transaction_amount.amount.clone(),
+                currency: tx.transaction_amount.currency.clone(),
+                account: account.clone(),
+            };
+            let text = ledger::render(dialect, &entry);
+            if let Err(err) = writeln!(out, "{}", text) {
+                return Err(format!("{}", err));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An account's transactions, working around its bank's quirks, if any.
+async fn fetch_transactions(
+    token: &str,
+    account_id: &str,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    quirk: quirks::Quirk,
+) -> Result<banks::Transactions, NordigenError> {
+    let mut res = banks::Transactions::default();
+    for (from, to) in quirk.ranges(from, to) {
+        let mut txns = banks::transactions(token, account_id, from, to).await?;
+        quirk.fix(&mut txns, from, to);
+        res.booked.extend(txns.booked);
+        // pending ones are the same in every range
+        res.pending = txns.pending;
+    }
+    Ok(res)
+}
+
+/// What fetching the data of a bank's accounts needs, including the bank
+/// to authorize again should its access turn out to have expired.
+struct Fetcher<'a> {
+    token: &'a str,
+    bankstate: &'a BankAuthState,
+    bank: &'a BankSource,
+    /// The bank's accounts once authorized again, under their new ids.
+    renewed: std::cell::RefCell<Option<Vec<banks::Account>>>,
+}
+
+impl<'a> Fetcher<'a> {
+    fn new(
+        token: &'a str,
+        bankstate: &'a BankAuthState,
+        bank: &'a BankSource,
+    ) -> Fetcher<'a> {
+        Fetcher {
+            token,
+            bankstate,
+            bank,
+            renewed: std::cell::RefCell::new(None),
+        }
+    }
+
+    /// The id to fetch `account`'s data by: its own, or that of the same
+    /// account since the bank was authorized again.
+    fn account_id(&self, account: &banks::Account) -> String {
+        let renewed = self.renewed.borrow();
+        let accounts = match renewed.as_ref() {
+            None => return account.meta.id.clone(),
+            Some(accounts) => accounts,
+        };
+        match accounts
+            .iter()
+            .find(|e| e.identifier() == account.identifier())
+        {
+            Some(e) => e.meta.id.clone(),
+            None => {
+                eprintln!(
+                    "{} is no longer among the bank's accounts.",
+                    account.identifier()
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Authorize the bank again after `err`, if it's due to access having
+    /// expired, and only once; exits otherwise.
+    async fn renew_or_exit(&self, err: &NordigenError) {
+        if !err.is_access_expired() || self.renewed.borrow().is_some() {
+            std::process::exit(err.exit_code());
+        }
+        let bankstate =
+            reauthorize(self.token, self.bankstate, self.bank).await;
+        let accounts =
+            banks::accounts(self.token, &bankstate.requisition.requisition_id)
+                .await
+                .unwrap_or_else(|err| {
+                    eprintln!("Error obtaining accounts metadata: {}", err);
+                    std::process::exit(err.exit_code());
+                });
+        let accounts = cache_accounts(&bankstate, self.bank, accounts);
+        *self.renewed.borrow_mut() = Some(accounts);
+    }
+
+    async fn balances_or_exit(
+        &self,
+        account: &banks::Account,
+    ) -> Vec<banks::Balance> {
+        loop {
+            let account_id = self.account_id(account);
+            match banks::balances(self.token, &account_id).await {
+                Ok(res) => return res,
+                Err(err) => {
+                    eprintln!("Error obtaining balances: {}", err);
+                    self.renew_or_exit(&err).await;
+                }
+            }
+        }
+    }
+
+    async fn details_or_exit(
+        &self,
+        account: &banks::Account,
+    ) -> banks::AccountDetails {
+        loop {
+            let account_id = self.account_id(account);
+            match banks::account_details(self.token, &account_id).await {
+                Ok(res) => return res,
+                Err(err) => {
+                    eprintln!("Error obtaining account details: {}", err);
+                    self.renew_or_exit(&err).await;
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_transactions_or_exit<'a>(
+    fetcher: &Fetcher<'_>,
+    accounts: &[&'a banks::Account],
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    quirks: &Quirks,
+) -> Vec<(&'a banks::Account, banks::Transactions)> {
+    loop {
+        let progress = Progress::start("transactions");
+        let total = accounts.len();
+        let done = std::sync::atomic::AtomicUsize::new(0);
+        let results =
+            futures::future::join_all(accounts.iter().map(|account| {
+                let quirk = quirks.get(&account.meta.institution_id);
+                let account_id = fetcher.account_id(account);
+                let (progress, done) = (&progress, &done);
+                async move {
+                    let res = fetch_transactions(
+                        fetcher.token,
+                        &account_id,
+                        from,
+                        to,
+                        quirk,
+                    )
+                    .await;
+                    let done =
+                        done.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    progress.advance(done + 1, total);
+                    res
+                }
+            }))
+            .await;
+        quota::save();
+
+        let mut fetched = vec![];
+        let mut failed = None;
+        for (account, res) in accounts.iter().zip(results) {
+            match res {
+                Err(err) => {
+                    eprintln!(
+                        "Error obtaining transactions for {}: {}",
+                        account.identifier(),
+                        err
+                    );
+                    failed = Some(err);
+                    break;
+                }
+                Ok(txns) => fetched.push((*account, txns)),
+            };
+        }
+        match failed {
+            None => {
+                progress.finish(true);
+                return fetched;
+            }
+            Some(err) => {
+                progress.finish(false);
+                fetcher.renew_or_exit(&err).await;
+            }
+        }
+    }
+}
+
+/// Requests for transactions `cmd` will make of an account: one per
+/// chunk, times the ranges its bank's `quirk` splits each into.
+fn transaction_requests(
+    cmd: &BankAccountTransactionsCmd,
+    quirk: &quirks::Quirk,
+) -> usize {
+    match (cmd.chunk, cmd.from) {
+        (Some(chunk), Some(from)) => {
+            let to =
+                cmd.to.unwrap_or_else(|| chrono::Local::now().date_naive());
+            chunks::windows(from, to, chunk)
+                .iter()
+                .map(|w| quirk.ranges(Some(w.from), Some(w.to)).len())
+                .sum()
+        }
+        _ => quirk.ranges(cmd.from, cmd.to).len(),
+    }
+}
+
+/// Make sure the daily quotas of `accounts`, as last seen, cover the
+/// transactions requests `cmd` will make, or that the user wants to go
+/// ahead anyway; rather than failing half way through.
+fn preflight_or_exit(
+    cmd: &BankAccountTransactionsCmd,
+    accounts: &[&banks::Account],
+    quirks: &Quirks,
+) {
+    let needed: Vec<(&banks::Account, usize)> = accounts
+        .iter()
+        .map(|account| {
+            let quirk = quirks.get(&account.meta.institution_id);
+            (*account, transaction_requests(cmd, &quirk))
+        })
+        .collect();
+    let short = quota::shortfalls(&needed, "transactions");
+    if short.is_empty() {
+        return;
+    }
+    for entry in &short {
+        eprintln!(
+            "{} needs {} transactions requests, but has {} left until {}.",
+            entry.account.identifier(),
+            entry.needed,
+            entry.quota.remaining,
+            entry
+                .quota
+                .resets
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+    }
+    if cmd.force {
+        return;
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Not enough quota left; use --force to go ahead anyway.");
+        std::process::exit(4);
+    }
+    eprint!("Not enough quota left. Go ahead anyway? [y/N] ");
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err()
+        || !answer.trim().eq_ignore_ascii_case("y")
+    {
+        std::process::exit(4);
+    }
+}
+
+/// The transactions to show of each account, as per `cmd`'s filters.
+fn filter_transactions<'a>(
+    fetched: &'a [(&'a banks::Account, banks::Transactions)],
+    cmd: &BankAccountTransactionsCmd,
+) -> Vec<AccountTransactions<'a>> {
+    let wanted = |tx: &&banks::Transaction| {
+        let sepa = tx.sepa();
+        (cmd.creditor_id.is_none() || sepa.creditor_id == cmd.creditor_id)
+            && (cmd.mandate.is_none() || sepa.mandate_reference == cmd.mandate)
+    };
+    fetched
+        .iter()
+        .map(|(account, txns)| AccountTransactions {
+            account,
+            booked: if cmd.pending {
+                vec![]
+            } else {
+                txns.booked.iter().filter(wanted).collect()
+            },
+            pending: txns.pending.iter().filter(wanted).collect(),
+        })
+        .collect()
+}
+
+/// Exit status with `--fail-if-empty`, when no transactions were found.
+const EXIT_EMPTY: i32 = 6;
+
+/// How many transactions of `groups` the output includes: csv always has
+/// the pending ones, other formats only with `--pending` or `--all`.
+fn count_shown(
+    groups: &[AccountTransactions],
+    cmd: &BankAccountTransactionsCmd,
+) -> usize {
+    let with_pending = cmd.format == "csv" || cmd.pending || cmd.all;
+    groups
+        .iter()
+        .map(|group| {
+            group.booked.len()
+                + if with_pending { group.pending.len() } else { 0 }
+        })
+        .sum()
+}
+
+/// Once output is written, exit with `EXIT_EMPTY` if asked to and nothing
+/// was found.
+fn exit_if_empty(cmd: &BankAccountTransactionsCmd, found: usize) {
+    if found == 0 && cmd.fail_if_empty {
+        info!("No transactions found.");
+        std::process::exit(EXIT_EMPTY);
+    }
+}
+
+/// Transactions to write to one period's csv file. Pending ones come back
+/// for every period, so only the last one, the most recent, gets them.
+fn chunk_transactions<'a>(
+    fetched: &'a [(&'a banks::Account, banks::Transactions)],
+    cmd: &BankAccountTransactionsCmd,
+    last: bool,
+) -> Vec<AccountTransactions<'a>> {
+    let mut groups = filter_transactions(fetched, cmd);
+    if !last {
+        for group in &mut groups {
+            group.pending.clear();
+        }
+    }
+    groups
+}
+
+/// Export to one csv file per period, each fetched on its own so no
+/// request covers more than a period.
+async fn export_transaction_chunks(
+    cmd: &BankAccountTransactionsCmd,
+    chunk: chunks::Chunk,
+    fetcher: &Fetcher<'_>,
+    accounts: &[&banks::Account],
+    quirks: &Quirks,
+    label: &dyn Fn(&banks::Transaction) -> String,
+) -> usize {
+    let (from, output) = match (cmd.from, &cmd.output) {
+        (Some(from), Some(output)) if cmd.format == "csv" => (from, output),
+        _ => {
+            eprintln!("--chunk requires --format csv, --from and --output.");
+            std::process::exit(1);
+        }
+    };
+    if is_stdio(output) {
+        eprintln!("--chunk writes one file per period; stdout won't do.");
+        std::process::exit(1);
+    }
+    let to = cmd.to.unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    let mut found = 0;
+    let windows = chunks::windows(from, to, chunk);
+    for (idx, window) in windows.iter().enumerate() {
+        let fetched = fetch_transactions_or_exit(
+            fetcher,
+            accounts,
+            Some(window.from),
+            Some(window.to),
+            quirks,
+        )
+        .await;
+        let last = idx + 1 == windows.len();
+        let groups = chunk_transactions(&fetched, cmd, last);
+        found += count_shown(&groups, cmd);
+
+        let path = chunks::chunk_path(output, &window.label);
+        let out = create_file(&path).unwrap_or_else(|err| {
+            eprintln!("Unable to open {} for writing: {}", path.display(), err);
+            std::process::exit(1);
+        });
+        write_transactions_csv(out, &groups, true, label).unwrap_or_else(
+            |err| {
+                eprintln!("Error writing csv: {}", err);
+                std::process::exit(1);
+            },
+        );
+        info!("Wrote {}", path.display());
+    }
+    found
+}
+
+/// How a transaction's counterparty is shown: by its contact's label, if
+/// any.
+fn contact_label(contacts: &Contacts, tx: &banks::Transaction) -> String {
+    let (name, iban) = tx.counterparty();
+    match contacts.lookup(name, iban) {
+        Some(contact) => contact.label.clone(),
+        None => String::from(name.or(iban).unwrap_or("<unknown>")),
+    }
+}
+
+fn contact_category(
+    contacts: &Contacts,
+    tx: &banks::Transaction,
+) -> Option<String> {
+    let (name, iban) = tx.counterparty();
+    contacts.lookup(name, iban)?.category.clone()
+}
+
+/// Export what was booked since each account's previous run with
+/// `--since-last-export`, up to yesterday; today is left for the next run,
+/// as more may yet be booked on it.
+async fn export_since_last_export(
+    cmd: &BankAccountTransactionsCmd,
+    fetcher: &Fetcher<'_>,
+    accounts: &[&banks::Account],
+    quirks: &Quirks,
+    ledger_settings: &settings::LedgerSettings,
+    contacts: &Contacts,
+    exports: &Option<std::path::PathBuf>,
+) -> usize {
+    let dialect = match cmd.format.as_str() {
+        "csv" => None,
+        "ledger" => Some(ledger::Dialect::Ledger),
+        "beancount" => Some(ledger::Dialect::Beancount),
+        _ => {
+            eprintln!(
+                "--since-last-export requires --format csv, ledger or \
+                 beancount."
+            );
+            std::process::exit(1);
+        }
+    };
+    let output = match &cmd.output {
+        Some(path) if !is_stdio(path) => path,
+        _ => {
+            eprintln!("--since-last-export writes to a file; stdout won't do.");
+            std::process::exit(1);
+        }
+    };
+    let exports = exports.as_ref().unwrap_or_else(|| {
+        eprintln!("Please specify --config-dir; HOME is not set.");
+        std::process::exit(1);
+    });
+    let mut watermarks = parse_watermarks(exports).unwrap_or_else(|err| {
+        eprintln!("Error reading previous exports: {}", err);
+        std::process::exit(1);
+    });
+    let to = chrono::Local::now().date_naive() - chrono::Duration::days(1);
+
+    let mut fetched = vec![];
+    for &account in accounts {
+        let from = match watermarks.get(&account.meta.id, &cmd.format) {
+            Some(last) => last.succ_opt(),
+            None => cmd.from,
+        };
+        if matches!(from, Some(from) if from > to) {
+            info!(
+                "{}: nothing new since the last export",
+                account.identifier()
+            );
+            fetched.push((account, banks::Transactions::default()));
+            continue;
+        }
+        fetched.extend(
+            fetch_transactions_or_exit(
+                fetcher,
+                &[account],
+                from,
+                Some(to),
+                quirks,
+            )
+            .await,
+        );
+    }
+    let mut groups = filter_transactions(&fetched, cmd);
+    // exported once booked, so as not to be exported twice
+    for group in &mut groups {
+        group.pending.clear();
+    }
+    let found = groups.iter().map(|g| g.booked.len()).sum();
+
+    let path = if cmd.append {
+        output.clone()
+    } else {
+        chunks::chunk_path(output, &to.to_string())
+    };
+    let with_header = !cmd.append
+        || std::fs::metadata(&path)
+            .map(|m| m.len() == 0)
+            .unwrap_or(true);
+    let out = if cmd.append {
+        append_file(&path)
+    } else {
+        create_file(&path)
+    };
+    let out = out.unwrap_or_else(|err| {
+        eprintln!("Unable to open {} for writing: {}", path.display(), err);
+        std::process::exit(1);
+    });
+    let label = |tx: &banks::Transaction| contact_label(contacts, tx);
+    let category = |tx: &banks::Transaction| contact_category(contacts, tx);
+    let res = match dialect {
+        None => write_transactions_csv(out, &groups, with_header, &label),
+        Some(dialect) => write_transactions_ledger(
+            out,
+            dialect,
+            &groups,
+            false,
+            ledger_settings,
+            &label,
+            &category,
+        ),
+    };
+    res.unwrap_or_else(|err| {
+        eprintln!("Error writing {}: {}", cmd.format, err);
+        std::process::exit(1);
+    });
+    info!("Wrote {}", path.display());
+
+    for &account in accounts {
+        watermarks.set(&account.meta.id, &cmd.format, to);
+    }
+    write_watermarks(&watermarks, exports).unwrap_or_else(|err| {
+        eprintln!("Error recording the export: {}", err);
+        std::process::exit(1);
+    });
+    found
+}
+
+async fn do_bank_account_transactions(
+    cmd: &BankAccountTransactionsCmd,
+    source: &StateSource,
+    bank: &BankSource,
+    configpath: &Option<std::path::PathBuf>,
+    exports: &Option<std::path::PathBuf>,
+) {
+    if let (Some(from), Some(to)) = (cmd.from, cmd.to) {
+        if from > to {
+            eprintln!("--from {} is after --to {}", from, to);
+            std::process::exit(1);
+        }
+    }
+
+    let token = get_token_or_exit(source).await;
+
+    let bankstate = load_bank_or_exit(bank);
+
+    let settings = get_settings_or_exit(configpath);
+    let quirks = Quirks::load(&settings.quirks).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let contacts = match &cmd.contacts {
+        None => Contacts::default(),
+        Some(path) => parse_contacts(path).unwrap_or_else(|err| {
+            eprintln!("Error parsing contacts: {}", err);
+            std::process::exit(1);
+        }),
+    };
+
+    // Without a selector, every account in the requisition, or those in
+    // the given currency.
+    let accounts = get_accounts_or_exit(
+        &token,
+        &bankstate,
+        bank,
+        cmd.account.refresh_accounts,
+    )
+    .await;
+    let members = cmd.group.as_ref().map(|name| {
+        settings.group(name).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+    });
+    let selected: Vec<&banks::Account> = if cmd.account.is_given() {
+        vec![select_account(&accounts, &cmd.account)]
+    } else {
+        accounts
+            .iter()
+            .filter(|e| match &cmd.account.currency {
+                None => true,
+                Some(currency) => e.has_currency(currency),
+            })
+            .filter(|e| match members {
+                None => true,
+                Some(members) => settings.is_member(members, e),
+            })
+            .collect()
+    };
+    let multiple = selected.len() > 1;
+    preflight_or_exit(cmd, &selected, &quirks);
+    let fetcher = Fetcher::new(&token, &bankstate, bank);
+
+    let label = |tx: &banks::Transaction| contact_label(&contacts, tx);
+
+    if let Some(chunk) = cmd.chunk {
+        let found = export_transaction_chunks(
+            cmd, chunk, &fetcher, &selected, &quirks, &label,
+        )
+        .await;
+        exit_if_empty(cmd, found);
+        return;
+    }
+
+    if cmd.since_last_export {
+        let found = export_since_last_export(
+            cmd,
+            &fetcher,
+            &selected,
+            &quirks,
+            &settings.ledger,
+            &contacts,
+            exports,
+        )
+        .await;
+        exit_if_empty(cmd, found);
+        return;
+    }
+
+    let fetched = fetch_transactions_or_exit(
+        &fetcher, &selected, cmd.from, cmd.to, &quirks,
+    )
+    .await;
+    let groups = filter_transactions(&fetched, cmd);
+    let found = count_shown(&groups, cmd);
+
+    let dialect = match cmd.format.as_str() {
+        "ledger" => Some(ledger::Dialect::Ledger),
+        "beancount" => Some(ledger::Dialect::Beancount),
+        _ => None,
+    };
+    if cmd.format == "csv" || dialect.is_some() {
+        let path = match &cmd.output {
+            None => std::path::PathBuf::from("-"),
+            Some(path) => path.clone(),
+        };
+        let out = create_file(&path).unwrap_or_else(|err| {
+            eprintln!("Unable to open {} for writing: {}", path.display(), err);
+            std::process::exit(1);
+        });
+        let res = match dialect {
+            None => write_transactions_csv(out, &groups, true, &label),
+            Some(dialect) => {
+                let category =
+                    |tx: &banks::Transaction| contact_category(&contacts, tx);
+                write_transactions_ledger(
+                    out,
+                    dialect,
+                    &groups,
+                    cmd.pending || cmd.all,
+                    &settings.ledger,
+                    &label,
+                    &category,
+                )
+            }
+        };
+        res.unwrap_or_else(|err| {
+            eprintln!("Error writing {}: {}", cmd.format, err);
+            std::process::exit(1);
+        });
+        exit_if_empty(cmd, found);
+        return;
+    }
+
+    let template = cmd.template.as_ref().map(|path| {
+        read_file(path).unwrap_or_else(|err| {
+            eprintln!("Error reading template: {}", err);
+            std::process::exit(1);
+        })
+    });
+
+    // Across the accounts of a --group, by currency.
+    let mut totals: BTreeMap<&str, summary::Summary> = BTreeMap::new();
+    for (n, group) in groups.iter().enumerate() {
+        let mut summary = summary::Summary::default();
+        for tx in &group.booked {
+            match tx.amount() {
+                None => {
+                    eprintln!(
+                        "Unable to parse transaction amount '{}'",
+                        tx.transaction_amount.amount
+                    );
+                }
+                Some(value) => {
+                    summary.add(value);
+                    totals
+                        .entry(&tx.transaction_amount.currency)
+                        .or_default()
+                        .add(value);
+                }
+            };
+        }
+
+        if let Some(template) = &template {
+            print_transactions_template(cmd, template, group, &summary, &label);
+            continue;
+        }
+
+        if multiple {
+            if n > 0 {
+                println!();
+            }
+            println!("== {} ==", group.account.identifier());
+        }
+        if cmd.format == "table" {
+            print_transactions_table(cmd, group, &summary, &label);
+        } else {
+            print_transactions_text(cmd, group, &summary, &label);
+        }
+    }
+    if let (Some(name), true, None) = (&cmd.group, cmd.summary, &template) {
+        for (currency, summary) in &totals {
+            println!();
+            println!("== {} total, {} ==", name, currency);
+            println!("{}", summary);
+        }
+    }
+    exit_if_empty(cmd, found);
+}
+
+fn print_transactions_template(
+    cmd: &BankAccountTransactionsCmd,
+    template: &str,
+    group: &AccountTransactions,
+    summary: &summary::Summary,
+    label: &dyn Fn(&banks::Transaction) -> String,
+) {
+    let render = |txns: &[&banks::Transaction]| -> Vec<serde_json::Value> {
+        txns.iter()
+            .map(|&tx| {
+                let mut value = serde_json::json!(tx);
+                value["counterparty"] = serde_json::json!(label(tx));
+                value["sepa"] = serde_json::json!(tx.sepa());
+                value
+            })
+            .collect()
+    };
+    let pending = if cmd.pending || cmd.all {
+        render(&group.pending)
+    } else {
+        vec![]
+    };
+    let context = serde_json::json!({
+        "account": group.account,
+        "transactions": render(&group.booked),
+        "pending": pending,
+        "summary": {
+            "count": summary.count,
+            "debits": summary.debits,
+            "credits": summary.credits,
+            "net": summary.net(),
+            "round_up": summary.round_up,
+        },
     });
+
+    let out = handlebars::Handlebars::new()
+        .render_template(template, &context)
+        .unwrap_or_else(|err| {
+            eprintln!("Error rendering template: {}", err);
+            std::process::exit(1);
+        });
+    print!("{}", out);
+}
+
+fn print_transactions_text(
+    cmd: &BankAccountTransactionsCmd,
+    group: &AccountTransactions,
+    summary: &summary::Summary,
+    label: &dyn Fn(&banks::Transaction) -> String,
+) {
+    let print = |tx: &banks::Transaction| {
+        let date = match tx.date() {
+            None => String::from("<unknown>"),
+            Some(val) => val.to_string(),
+        };
+        let info = match tx.info() {
+            None => String::from("<none>"),
+            Some(val) => val,
+        };
+        if !cmd.sepa {
+            println!(
+                "{}  {}  {}  {}",
+                date,
+                tx.transaction_amount.amount,
+                label(tx),
+                info
+            );
+            return;
+        }
+
+        let sepa = tx.sepa();
+        println!(
+            "{}  {}  {}  {}  cred={} mref={} eref={}",
+            date,
+            tx.transaction_amount.amount,
+            label(tx),
+            info,
+            sepa.creditor_id.as_deref().unwrap_or("-"),
+            sepa.mandate_reference.as_deref().unwrap_or("-"),
+            sepa.end_to_end_id.as_deref().unwrap_or("-")
+        );
+    };
+
+    if cmd.all {
+        println!("Booked:");
+    }
+    group.booked.iter().for_each(|&tx| print(tx));
+    if cmd.all {
+        println!();
+        println!("Pending:");
+    }
+    if cmd.pending || cmd.all {
+        group.pending.iter().for_each(|&tx| print(tx));
+    }
+
+    if cmd.summary {
+        println!();
+        println!("{}", summary);
+    }
+    if cmd.round_up {
+        println!();
+        println!("    round-up: {}", summary.round_up);
+    }
+}
+
+/// Longest transaction information shown in a table, as remittance
+/// information may run for hundreds of characters.
+const TABLE_INFO_WIDTH: usize = 40;
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut res: String = text.chars().take(width - 1).collect();
+    res.push('…');
+    res
+}
+
+/// `txns` as a table, with their debits, credits and net at the foot.
+fn transactions_table(
+    cmd: &BankAccountTransactionsCmd,
+    txns: &[&banks::Transaction],
+    label: &dyn Fn(&banks::Transaction) -> String,
+) -> Table {
+    let mut header = vec!["Date", "Amount", "Currency", "Counterparty", "Info"];
+    if cmd.sepa {
+        header.extend(["Creditor", "Mandate", "End-to-end"]);
+    }
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        header
+            .iter()
+            .map(|h| Cell::new(h).with_style(Attr::Bold))
+            .collect(),
+    ));
+
+    let mut summary = summary::Summary::default();
+    for &tx in txns {
+        if let Some(value) = tx.amount() {
+            summary.add(value);
+        }
+        let date = match tx.date() {
+            None => String::from("<unknown>"),
+            Some(val) => val.to_string(),
+        };
+        let info = match tx.info() {
+            None => String::from("<none>"),
+            Some(val) => truncate(&val, TABLE_INFO_WIDTH),
+        };
+        let mut row = row![
+            date,
+            r->tx.transaction_amount.amount,
+            tx.transaction_amount.currency,
+            label(tx),
+            info
+        ];
+        if cmd.sepa {
+            let sepa = tx.sepa();
+            for value in
+                [sepa.creditor_id, sepa.mandate_reference, sepa.end_to_end_id]
+            {
+                row.add_cell(Cell::new(value.as_deref().unwrap_or("-")));
+            }
+        }
+        table.add_row(row);
+    }
+
+    let currency = txns
+        .first()
+        .map(|tx| tx.transaction_amount.currency.as_str())
+        .unwrap_or("");
+    for (name, value) in [
+        ("Debits", summary.debits),
+        ("Credits", summary.credits),
+        ("Net", summary.net()),
+    ] {
+        table.add_row(Row::new(vec![
+            Cell::new(name).with_style(Attr::Bold),
+            Cell::new_align(
+                &value.to_string(),
+                prettytable::format::Alignment::RIGHT,
+            )
+            .with_style(Attr::Bold),
+            Cell::new(currency),
+        ]));
+    }
+    table
+}
+
+fn print_transactions_table(
+    cmd: &BankAccountTransactionsCmd,
+    group: &AccountTransactions,
+    summary: &summary::Summary,
+    label: &dyn Fn(&banks::Transaction) -> String,
+) {
+    if cmd.all {
+        println!("Booked:");
+    }
+    if !cmd.pending {
+        transactions_table(cmd, &group.booked, label).printstd();
+    }
+    if cmd.all {
+        println!();
+        println!("Pending:");
+    }
+    if cmd.pending || cmd.all {
+        transactions_table(cmd, &group.pending, label).printstd();
+    }
+
+    if cmd.summary {
+        println!();
+        println!("{}", summary);
+    }
+    if cmd.round_up {
+        println!();
+        println!("    round-up: {}", summary.round_up);
+    }
+}
+
+async fn do_bank_account_balance(
+    cmd: &BankAccountBalanceCmd,
+    source: &StateSource,
+    bank: &BankSource,
+    configpath: &Option<std::path::PathBuf>,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let bankstate = load_bank_or_exit(bank);
+
+    let settings = get_settings_or_exit(configpath);
+    let accounts = get_accounts_or_exit(
+        &token,
+        &bankstate,
+        bank,
+        cmd.account.refresh_accounts,
+    )
+    .await;
+    let fetcher = Fetcher::new(&token, &bankstate, bank);
+    if let Some(name) = &cmd.group {
+        print_group_balances(cmd, &fetcher, &settings, &accounts, name).await;
+        return;
+    }
+    let selected = select_account(&accounts, &cmd.account);
+
+    let balances = fetcher.balances_or_exit(selected).await;
+
+    let types = if cmd.balance_type.is_empty() {
+        settings.balance_types(selected)
+    } else {
+        cmd.balance_type.clone()
+    };
+    let balance = match banks::preferred_balance(&balances, &types) {
+        None => {
+            eprintln!("{}", tr!(NoBalances));
+            std::process::exit(1);
+        }
+        Some(res) => res,
+    };
+
+    if cmd.all {
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new(""),
+            Cell::new("Type").with_style(Attr::Bold),
+            Cell::new("Amount").with_style(Attr::Bold),
+            Cell::new("Currency").with_style(Attr::Bold),
+            Cell::new("Reference Date").with_style(Attr::Bold),
+        ]));
+        for entry in &balances {
+            let mark = if std::ptr::eq(entry, balance) {
+                "*"
+            } else {
+                ""
+            };
+            let reference_date = match entry.reference_date {
+                None => String::from("unknown"),
+                Some(val) => val.to_string(),
+            };
+            table.add_row(row![
+                mark,
+                entry.balance_type,
+                r->entry.balance_amount.amount,
+                entry.balance_amount.currency,
+                reference_date
+            ]);
+        }
+        table.printstd();
+        return;
+    }
+
+    let reference_date = match balance.reference_date {
+        None => String::from("unknown"),
+        Some(val) => val.to_string(),
+    };
     println!(
-        "Please follow the link below to authenticate with the selected bank."
+        "{} {}  ({}, as of {})",
+        balance.balance_amount.amount,
+        balance.balance_amount.currency,
+        balance.balance_type,
+        reference_date
     );
-    println!("  {}", link);
+}
+
+/// The preferred balance of each account in the group `name`, followed
+/// by their total per currency.
+async fn print_group_balances(
+    cmd: &BankAccountBalanceCmd,
+    fetcher: &Fetcher<'_>,
+    settings: &Settings,
+    accounts: &[banks::Account],
+    name: &str,
+) {
+    let members = settings.group(name).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    let selected: Vec<&banks::Account> = accounts
+        .iter()
+        .filter(|e| settings.is_member(members, e))
+        .collect();
+    if selected.is_empty() {
+        eprintln!("No accounts of this bank are in group '{}'.", name);
+        std::process::exit(1);
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Account").with_style(Attr::Bold),
+        Cell::new("Amount").with_style(Attr::Bold),
+        Cell::new("Currency").with_style(Attr::Bold),
+        Cell::new("Type").with_style(Attr::Bold),
+    ]));
+    let mut totals: BTreeMap<String, Amount> = BTreeMap::new();
+    for account in selected {
+        let balances = fetcher.balances_or_exit(account).await;
+        let types = if cmd.balance_type.is_empty() {
+            settings.balance_types(account)
+        } else {
+            cmd.balance_type.clone()
+        };
+        let balance = match banks::preferred_balance(&balances, &types) {
+            None => {
+                eprintln!("{}: {}", account.identifier(), tr!(NoBalances));
+                continue;
+            }
+            Some(res) => res,
+        };
+        let amount = &balance.balance_amount;
+        match amount.amount.parse::<Amount>() {
+            Err(_) => {
+                eprintln!("Unable to parse balance amount '{}'", amount.amount)
+            }
+            Ok(value) => {
+                *totals.entry(amount.currency.clone()).or_default() += value
+            }
+        };
+        table.add_row(row![
+            account.identifier(),
+            r->amount.amount,
+            amount.currency,
+            balance.balance_type
+        ]);
+    }
+    for (currency, total) in &totals {
+        table.add_row(row![
+            format!("{} total", name),
+            r->total.to_string(),
+            currency,
+            ""
+        ]);
+    }
+    table.printstd();
+}
+
+async fn do_bank_account_show(
+    cmd: &BankAccountShowCmd,
+    source: &StateSource,
+    bank: &BankSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let bankstate = load_bank_or_exit(bank);
+
+    let accounts = get_accounts_or_exit(
+        &token,
+        &bankstate,
+        bank,
+        cmd.account.refresh_accounts,
+    )
+    .await;
+    let selected = select_account(&accounts, &cmd.account);
+    let meta = &selected.meta;
+    let details = &selected.details;
+
+    if cmd.json {
+        let out =
+            serde_json::to_string_pretty(selected).unwrap_or_else(|err| {
+                eprintln!("Unable to serialize account details: {}", err);
+                std::process::exit(1);
+            });
+        println!("{}", out);
+        return;
+    }
+
+    println!("   account id: {}", meta.id);
+    if let Some(iban) = &meta.iban {
+        println!("         iban: {}", iban);
+    }
+    if let Some(pan) = &details.masked_pan {
+        println!("   masked pan: {}", pan);
+    }
+    if let Some(currency) = &details.currency {
+        println!("     currency: {}", currency);
+    }
+    println!("      bank id: {}", meta.institution_id);
+    if let Some(name) = &details.name {
+        println!(" account name: {}", name);
+    }
+    if let Some(product) = &details.product {
+        println!("      product: {}", product);
+    }
+    if let Some(cash_type) = &details.cash_account_type {
+        println!(" account type: {}", cash_type);
+    }
+    if let Some(usage) = &details.usage {
+        println!("        usage: {}", usage);
+    }
+    match details.owner_name.as_ref().or(meta.owner_name.as_ref()) {
+        None => println!("        owner: unknown"),
+        Some(name) => println!("        owner: {}", name),
+    };
+    if let Some(addr) = &details.owner_address_structured {
+        println!("owner address: {}", addr);
+    } else if let Some(addr) = &details.owner_address_unstructured {
+        println!("owner address: {}", addr);
+    }
+}
+
+async fn do_bank_account_details(
+    cmd: &BankAccountDetailsCmd,
+    source: &StateSource,
+    bank: &BankSource,
+) {
+    let token = get_token_or_exit(source).await;
+
+    let bankstate = load_bank_or_exit(bank);
 
-    let requisition = auth.wait_callback().await.unwrap_or_else(|err| {
-        eprintln!("Error obtaining bank requisition: {}", err);
+    let accounts = get_accounts_or_exit(
+        &token,
+        &bankstate,
+        bank,
+        cmd.account.refresh_accounts,
+    )
+    .await;
+    let selected = select_account(&accounts, &cmd.account);
+    let fetcher = Fetcher::new(&token, &bankstate, bank);
+    let details = fetcher.details_or_exit(selected).await;
+
+    if cmd.json {
+        let out =
+            serde_json::to_string_pretty(&details).unwrap_or_else(|err| {
+                eprintln!("Unable to serialize account details: {}", err);
+                std::process::exit(1);
+            });
+        println!("{}", out);
+        return;
+    }
+
+    let address = match (
+        &details.owner_address_structured,
+        &details.owner_address_unstructured,
+    ) {
+        (Some(addr), _) => Some(addr.to_string()),
+        (None, Some(addr)) => Some(addr.to_string()),
+        (None, None) => None,
+    };
+    let fields = [
+        ("resource id", details.resource_id.clone()),
+        ("iban", details.iban.clone()),
+        ("bban", details.bban.clone()),
+        ("bic", details.bic.clone()),
+        ("msisdn", details.msisdn.clone()),
+        ("masked pan", details.masked_pan.clone()),
+        ("currency", details.currency.clone()),
+        ("account name", details.name.clone()),
+        ("display name", details.display_name.clone()),
+        ("product", details.product.clone()),
+        ("account type", details.cash_account_type.clone()),
+        ("status", details.status.clone()),
+        ("usage", details.usage.clone()),
+        ("details", details.details.clone()),
+        ("linked account", details.linked_accounts.clone()),
+        ("owner", details.owner_name.clone()),
+        ("owner address", address),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            println!("{:>14}: {}", label, value);
+        }
+    }
+}
+
+fn do_contacts_add(cmd: &ContactsAddCmd, path: &std::path::PathBuf) {
+    let mut contacts = if path.exists() {
+        parse_contacts(path).unwrap_or_else(|err| {
+            eprintln!("Error parsing contacts: {}", err);
+            std::process::exit(1);
+        })
+    } else {
+        Contacts::default()
+    };
+
+    contacts.add(
+        &cmd.key,
+        contacts::Contact {
+            label: cmd.label.clone(),
+            category: cmd.category.clone(),
+        },
+    );
+    write_contacts(&contacts, path).unwrap_or_else(|err| {
+        eprintln!("Error writing contacts: {}", err);
         std::process::exit(1);
     });
+}
 
-    let bank_state = banks::BankAuthState::new(&cmd.bank_id, &requisition);
-    write_bank(&bank_state, &cmd.auth).unwrap_or_else(|err| {
-        eprintln!("Error writing bank state: {}", err);
+fn do_contacts_list(path: &std::path::PathBuf) {
+    let contacts = parse_contacts(path).unwrap_or_else(|err| {
+        eprintln!("Error parsing contacts: {}", err);
         std::process::exit(1);
     });
-    println!("Successfully authorized with bank!");
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Counterparty").with_style(Attr::Bold),
+        Cell::new("Label").with_style(Attr::Bold),
+        Cell::new("Category").with_style(Attr::Bold),
+    ]));
+    for (key, contact) in &contacts.entries {
+        let category = match &contact.category {
+            None => String::from(""),
+            Some(val) => val.clone(),
+        };
+        table.add_row(row![key, contact.label, category]);
+    }
+    table.printstd();
 }
 
-async fn do_bank_account_list(
-    statepath: &std::path::PathBuf,
-    bankstatepath: &std::path::PathBuf,
-) {
-    let state = get_state_or_exit(statepath);
-    if state.is_token_expired() {
-        eprintln!("Token has expired. Maybe refresh?");
+fn do_contacts_remove(cmd: &ContactsRemoveCmd, path: &std::path::PathBuf) {
+    let mut contacts = parse_contacts(path).unwrap_or_else(|err| {
+        eprintln!("Error parsing contacts: {}", err);
         std::process::exit(1);
-    }
+    });
 
-    let bankstate = parse_bank(bankstatepath).unwrap_or_else(|err| {
-        eprintln!(
-            "Unable to read bank state file at {}: {}",
-            bankstatepath.display(),
-            err
-        );
+    if contacts.remove(&cmd.key).is_none() {
+        eprintln!("{}", tr!(ContactNotFound, cmd.key));
+        std::process::exit(1);
+    }
+    write_contacts(&contacts, path).unwrap_or_else(|err| {
+        eprintln!("Error writing contacts: {}", err);
         std::process::exit(1);
     });
+}
 
-    let acc = banks::Accounts::new(
-        &state.token,
-        &bankstate.requisition.requisition_id,
-    );
+fn get_bank_store_or_exit(path: &std::path::PathBuf) -> BankStore {
+    parse_bank_store(path).unwrap_or_else(|err| {
+        eprintln!("Unable to read banks store at {}: {}", path.display(), err);
+        std::process::exit(1);
+    })
+}
 
-    let acclst = acc.list().await.unwrap_or_else(|err| {
-        eprintln!("Unable to list accounts: {}", err);
+fn write_bank_store_or_exit(store: &BankStore, path: &std::path::PathBuf) {
+    write_bank_store(store, path).unwrap_or_else(|err| {
+        eprintln!("Unable to write banks store to disk: {}", err);
         std::process::exit(1);
     });
+}
 
-    for account in &acclst {
-        let meta = acc.meta(account).await.unwrap_or_else(|err| {
-            eprintln!(
-                "Error obtaining metadata for account {}: {}",
-                account, err
-            );
-            std::process::exit(1);
-        });
-
-        let created_at = match meta.created_at {
-            None => String::from("unknown"),
-            Some(val) => val.to_string(),
-        };
-        let accessed_at = match meta.accessed_at {
-            None => String::from("unknown"),
-            Some(val) => val.to_string(),
-        };
+fn do_bank_add(cmd: &BankAddCmd, storepath: &std::path::PathBuf) {
+    guard::deny_or_exit("change the banks store");
+    let bankstate = load_bank_or_exit(&BankSource::File(cmd.auth.clone()));
+    let mut store = get_bank_store_or_exit(storepath);
+    if store.contains(&cmd.alias) && !cmd.force {
+        eprintln!(
+            "Bank '{}' already exists; use --force to replace it.",
+            cmd.alias
+        );
+        std::process::exit(1);
+    }
+    store.insert(&cmd.alias, bankstate);
+    write_bank_store_or_exit(&store, storepath);
+    info!("Added bank '{}'.", cmd.alias);
+}
 
-        println!("");
-        println!("   account id: {}", meta.id);
-        println!("         iban: {}", meta.iban);
-        println!("     currency: {}", meta.currency);
-        println!("      bank id: {}", meta.institution_id);
-        if let Some(name) = meta.name {
-            println!(" account name: {}", name);
-        }
-        if let Some(name) = meta.owner_name {
-            println!("        owner: {}", name);
-        }
-        if let Some(product) = meta.product {
-            println!("      product: {}", product);
-        }
-        if let Some(account_type) = meta.account_type {
-            println!(" account type: {}", account_type);
-        }
-        println!("      created: {}", created_at);
-        println!("last accessed: {}", accessed_at);
-        println!("")
+fn do_bank_remove(cmd: &BankRemoveCmd, storepath: &std::path::PathBuf) {
+    guard::deny_or_exit("change the banks store");
+    let mut store = get_bank_store_or_exit(storepath);
+    if let Err(err) = store.remove(&cmd.alias) {
+        eprintln!("{}", err);
+        std::process::exit(1);
     }
+    write_bank_store_or_exit(&store, storepath);
+    info!("Removed bank '{}'.", cmd.alias);
 }
 
-async fn do_bank_account_transactions(
-    cmd: &BankAccountTransactionsCmd,
-    statepath: &std::path::PathBuf,
-    bankpath: &std::path::PathBuf,
-) {
-    let state = get_state_or_exit(statepath);
-    if state.is_token_expired() {
-        eprintln!("Token has expired. Maybe refresh?");
+fn do_bank_rename(cmd: &BankRenameCmd, storepath: &std::path::PathBuf) {
+    guard::deny_or_exit("change the banks store");
+    let mut store = get_bank_store_or_exit(storepath);
+    if let Err(err) = store.rename(&cmd.alias, &cmd.new_alias) {
+        eprintln!("{}", err);
         std::process::exit(1);
     }
+    write_bank_store_or_exit(&store, storepath);
+    info!("Renamed bank '{}' to '{}'.", cmd.alias, cmd.new_alias);
+}
 
-    let bankstate = parse_bank(bankpath).unwrap_or_else(|err| {
-        eprintln!(
-            "Unable to read bank state file at {}: {}",
-            bankpath.display(),
-            err
-        );
+/// The bank selected by `--bank`, in the banks store, or else by `--auth`.
+fn bank_source_or_exit(
+    cmd: &BankCmd,
+    dir: &Option<std::path::PathBuf>,
+    auth: &Option<std::path::PathBuf>,
+    alias: &Option<String>,
+) -> BankSource {
+    match alias {
+        Some(alias) => BankSource::Store {
+            path: resolve_or_exit(
+                &cmd.banks,
+                dir,
+                paths::BANKS_FILE,
+                "--banks",
+            ),
+            alias: alias.clone(),
+        },
+        None => BankSource::File(resolve_or_exit(
+            auth,
+            dir,
+            paths::BANK_FILE,
+            "--auth",
+        )),
+    }
+}
+
+fn do_profile(cmd: &ProfileCmd) {
+    match &cmd.command {
+        ProfileCmds::List(_) => do_profile_list(),
+        ProfileCmds::Create(createcmd) => do_profile_create(createcmd),
+        ProfileCmds::Delete(delcmd) => do_profile_delete(delcmd),
+    }
+}
+
+fn do_profile_list() {
+    let names = profiles::list().unwrap_or_else(|err| {
+        eprintln!("{}", err);
         std::process::exit(1);
     });
+    if names.is_empty() {
+        info!("No profiles.");
+    }
+    for name in names {
+        println!("{}", name);
+    }
+}
 
-    let acc = banks::Accounts::new(
-        &state.token,
-        &bankstate.requisition.requisition_id,
-    );
-
-    let meta_vec = acc.meta_all().await.unwrap_or_else(|err| {
-        eprintln!("Error obtaining accounts metadata: {}", err);
+fn do_profile_create(cmd: &ProfileCreateCmd) {
+    guard::deny_or_exit("create a profile");
+    let dir = profiles::create(&cmd.name).unwrap_or_else(|err| {
+        eprintln!("{}", err);
         std::process::exit(1);
     });
-    let meta = &meta_vec
-        .iter()
-        .filter(|entry| entry.iban == cmd.iban)
-        .take(1)
-        .next();
+    info!(
+        "Created profile '{}' at {}; set it up with `nordigen-cli --profile \
+         {} init`.",
+        cmd.name,
+        dir.display(),
+        cmd.name
+    );
+}
 
-    let selected = match meta {
-        None => {
-            eprintln!("Could not find account with IBAN {}", cmd.iban);
+fn do_profile_delete(cmd: &ProfileDeleteCmd) {
+    guard::deny_or_exit("delete a profile");
+    if !cmd.yes {
+        if !std::io::stdin().is_terminal() {
+            eprintln!(
+                "Refusing to delete profile '{}' without --yes.",
+                cmd.name
+            );
             std::process::exit(1);
         }
-        Some(res) => res,
-    };
-
-    let txns = acc.transactions(&selected.id).await.unwrap_or_else(|err| {
-        eprintln!("Error obtaining transactions: {}", err);
+        eprint!(
+            "Delete profile '{}', with its state and bank authorizations? \
+             [y/N] ",
+            cmd.name
+        );
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err()
+            || !answer.trim().eq_ignore_ascii_case("y")
+        {
+            std::process::exit(1);
+        }
+    }
+    profiles::delete(&cmd.name).unwrap_or_else(|err| {
+        eprintln!("{}", err);
         std::process::exit(1);
     });
+    info!("Deleted profile '{}'.", cmd.name);
+}
 
-    for tx in &txns.booked {
-        let info = match &tx.remittance_information_unstructured {
-            None => String::from("<none>"),
-            Some(val) => val.clone(),
-        };
-        println!(
-            "{}  {}  {}",
-            tx.value_date, tx.transaction_amount.amount, info
-        )
+fn do_contacts(cmd: &ContactsCmd) {
+    match &cmd.command {
+        ContactsCmds::Add(addcmd) => do_contacts_add(addcmd, &cmd.file),
+        ContactsCmds::List(_) => do_contacts_list(&cmd.file),
+        ContactsCmds::Remove(rmcmd) => do_contacts_remove(rmcmd, &cmd.file),
     }
 }
 
-async fn do_bank_account_balance(
-    cmd: &BankAccountBalanceCmd,
-    statepath: &std::path::PathBuf,
-    bankpath: &std::path::PathBuf,
-) {
-    let state = get_state_or_exit(statepath);
-    if state.is_token_expired() {
-        eprintln!("Token has expired. Maybe refresh?");
+fn do_schema(cmd: &SchemaCmd) {
+    let value = match &cmd.model {
+        Some(model) => serde_json::json!(schema::schema(model)),
+        None => serde_json::Value::Object(
+            schema::MODELS
+                .iter()
+                .map(|&m| (m.to_string(), serde_json::json!(schema::schema(m))))
+                .collect(),
+        ),
+    };
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+}
+
+fn do_fixtures_anonymize(cmd: &FixturesAnonymizeCmd) {
+    if cmd.output_dir.is_none() && cmd.files.len() > 1 {
+        eprintln!("Multiple files require --output-dir");
         std::process::exit(1);
     }
-
-    let bankstate = parse_bank(bankpath).unwrap_or_else(|err| {
-        eprintln!(
-            "Unable to read bank state file at {}: {}",
-            bankpath.display(),
-            err
-        );
+    if cmd.files.iter().filter(|f| is_stdio(f)).count() > 1 {
+        eprintln!("Only one file may be read from stdin.");
         std::process::exit(1);
-    });
-
-    let accnt = banks::Accounts::new(
-        &state.token,
-        &bankstate.requisition.requisition_id,
-    );
+    }
 
-    let meta_vec = accnt.meta_all().await.unwrap_or_else(|err| {
-        eprintln!("Error obtaining accounts metadata: {}", err);
-        std::process::exit(1);
-    });
-    let meta = &meta_vec
-        .iter()
-        .filter(|entry| entry.iban == cmd.iban)
-        .take(1)
-        .next();
+    let anonymizer = fixtures::Anonymizer::new(&cmd.seed);
+    for file in &cmd.files {
+        let contents = read_file(file).unwrap_or_else(|err| {
+            eprintln!("Error reading response: {}", err);
+            std::process::exit(1);
+        });
+        let mut value: serde_json::Value = serde_json::from_str(&contents)
+            .unwrap_or_else(|err| {
+                eprintln!("Error parsing {}: {}", file.display(), err);
+                std::process::exit(1);
+            });
+        anonymizer.rewrite(&mut value);
 
-    let selected = match meta {
-        None => {
-            eprintln!("Could not find account with IBAN {}", cmd.iban);
+        let path = match &cmd.output_dir {
+            None => std::path::PathBuf::from("-"),
+            Some(dir) => match file.file_name() {
+                Some(name) if !is_stdio(file) => dir.join(name),
+                _ => dir.join("stdin.json"),
+            },
+        };
+        let mut out = create_file(&path).unwrap_or_else(|err| {
+            eprintln!("Error creating {}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+        let json = serde_json::to_string_pretty(&value).unwrap();
+        if let Err(err) = writeln!(out, "{}", json) {
+            eprintln!("Error writing {}: {}", path.display(), err);
             std::process::exit(1);
         }
-        Some(res) => res,
-    };
-
-    accnt.balance(&selected.id).await;
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    let command = match &cli.command {
+        _ if cli.version => {
+            version::print(cli.json);
+            return;
+        }
+        None => {
+            let mut cmd = <Cli as clap::CommandFactory>::command();
+            eprintln!("{}", cmd.render_help());
+            std::process::exit(2);
+        }
+        Some(command) => command,
+    };
+    msg::set_quiet(cli.quiet);
+    msg::set_progress(cli.progress);
+    session::set_refresh_margin(cli.refresh_margin);
+    let passphrase = match &cli.passphrase_file {
+        None => secret_from_env(PASSPHRASE_VAR),
+        Some(path) => Some(
+            read_file(path)
+                .unwrap_or_else(|err| {
+                    eprintln!("Error reading passphrase: {}", err);
+                    std::process::exit(1);
+                })
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        ),
+    };
+    match passphrase {
+        Some(passphrase) if passphrase.is_empty() => {
+            eprintln!("The passphrase is empty.");
+            std::process::exit(1);
+        }
+        Some(passphrase) => crypt::set_passphrase(passphrase),
+        None => {}
+    }
+    if let Some(url) = &cli.base_url {
+        nordigen_cli::set_base_url(url);
+    }
+    logging::init(cli.verbose, &cli.log_level, &cli.log_file).unwrap_or_else(
+        |err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    );
+    if let Some(path) = &cli.trace_http {
+        trace::set_trace_file(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+    }
+    i18n::set_lang(match cli.lang {
+        None => i18n::detect(),
+        Some(lang) => lang,
+    });
 
-    match &cli.command {
+    let dir = &match &cli.profile {
+        None => cli.config_dir.clone(),
+        Some(_) if matches!(command, Commands::Profile(_)) => None,
+        Some(name) => {
+            keychain::set_profile(name);
+            Some(profiles::dir(name).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }))
+        }
+    };
+    let config = match command {
+        Commands::Init(cmd) => &cmd.config,
+        Commands::Authorize(cmd) => &cmd.config,
+        Commands::RotateSecrets(cmd) => &cmd.config,
+        Commands::Bank(cmd) => &cmd.config,
+        _ => &None,
+    };
+    let read_only =
+        match paths::resolve_optional(config, dir, paths::CONFIG_FILE) {
+            Some(path) if !is_stdio(&path) && path.exists() => {
+                get_settings_or_exit(&Some(path)).read_only
+            }
+            _ => false,
+        };
+    guard::set_read_only(cli.read_only || read_only);
+    match command {
+        Commands::Init(cmd) => {
+            let config = resolve_or_exit(
+                &cmd.config,
+                dir,
+                paths::CONFIG_FILE,
+                "--config",
+            );
+            let state = if cli.ephemeral {
+                None
+            } else {
+                Some(resolve_or_exit(
+                    &cmd.state,
+                    dir,
+                    paths::STATE_FILE,
+                    "--state",
+                ))
+            };
+            do_init(cmd, &config, &state).await;
+        }
         Commands::Authorize(cmd) => {
-            do_authorize(cmd).await;
+            let config = resolve_or_exit(
+                &cmd.config,
+                dir,
+                paths::CONFIG_FILE,
+                "--config",
+            );
+            check_first_run(&config, None);
+            if cli.ephemeral {
+                do_authorize_ephemeral(&config).await;
+            } else {
+                let state = resolve_or_exit(
+                    &cmd.state,
+                    dir,
+                    paths::STATE_FILE,
+                    "--state",
+                );
+                do_authorize(&config, &state).await;
+            }
         }
         Commands::Refresh(cmd) => {
-            do_refresh(cmd).await;
-        }
-        Commands::Bank(cmd) => match &cmd.command {
-            BankCmds::List(bankcmd) => {
-                do_bank_list(bankcmd, &cmd.state).await;
+            if cli.ephemeral {
+                eprintln!("{}", tr!(EphemeralRefresh));
+                std::process::exit(0);
             }
-            BankCmds::Authorize(bankcmd) => {
-                do_bank_authorization(bankcmd, &cmd.state).await;
+            let state =
+                resolve_or_exit(&cmd.state, dir, paths::STATE_FILE, "--state");
+            if let Some(config) = paths::resolve(&None, dir, paths::CONFIG_FILE)
+            {
+                check_first_run(&config, Some(&state));
             }
-            BankCmds::Account(accntcmd) => match &accntcmd.command {
-                BankAccountCmds::List(_) => {
-                    do_bank_account_list(&cmd.state, &accntcmd.auth).await;
+            do_refresh(&state).await;
+        }
+        Commands::RotateSecrets(cmd) => {
+            let config = resolve_or_exit(
+                &cmd.config,
+                dir,
+                paths::CONFIG_FILE,
+                "--config",
+            );
+            let state = if cli.ephemeral {
+                None
+            } else {
+                Some(resolve_or_exit(
+                    &cmd.state,
+                    dir,
+                    paths::STATE_FILE,
+                    "--state",
+                ))
+            };
+            do_rotate_secrets(cmd, &config, &state).await;
+        }
+        Commands::Secrets(cmd) => match &cmd.command {
+            SecretsCmds::Store(storecmd) => do_secrets_store(storecmd).await,
+            SecretsCmds::Delete(_) => do_secrets_delete(),
+        },
+        Commands::Profile(cmd) => do_profile(cmd),
+        Commands::Contacts(cmd) => {
+            do_contacts(cmd);
+        }
+        Commands::Schema(cmd) => do_schema(cmd),
+        Commands::Fixtures(cmd) => match &cmd.command {
+            FixturesCmds::Anonymize(anoncmd) => do_fixtures_anonymize(anoncmd),
+        },
+        Commands::Bank(cmd) => {
+            let auth = match &cmd.command {
+                BankCmds::Account(accntcmd) => accntcmd.auth.clone(),
+                BankCmds::Status(statuscmd) => statuscmd.auth.clone(),
+                BankCmds::Add(addcmd) => Some(addcmd.auth.clone()),
+                _ => None,
+            };
+            check_stdin_or_exit(&[&cmd.state, &cmd.config, &cmd.banks, &auth]);
+
+            // Editing the banks store needs no token, nor even secrets.
+            let banks = || {
+                resolve_or_exit(&cmd.banks, dir, paths::BANKS_FILE, "--banks")
+            };
+            match &cmd.command {
+                BankCmds::Status(statuscmd) if statuscmd.all_profiles => {
+                    if cli.profile.is_some() {
+                        eprintln!(
+                            "--all-profiles can't be used with --profile."
+                        );
+                        std::process::exit(1);
+                    }
+                    return do_bank_status_all_profiles(cli.ephemeral).await;
                 }
-                BankAccountCmds::Transactions(txcmd) => {
-                    do_bank_account_transactions(
-                        &txcmd,
-                        &cmd.state,
-                        &accntcmd.auth,
-                    )
-                    .await;
+                BankCmds::Add(addcmd) => return do_bank_add(addcmd, &banks()),
+                BankCmds::Remove(rmcmd) => {
+                    return do_bank_remove(rmcmd, &banks());
+                }
+                BankCmds::Rename(mvcmd) => {
+                    return do_bank_rename(mvcmd, &banks());
+                }
+                _ => {}
+            };
+
+            let source = if cli.ephemeral {
+                let config = resolve_or_exit(
+                    &cmd.config,
+                    dir,
+                    paths::CONFIG_FILE,
+                    "--config",
+                );
+                check_first_run(&config, None);
+                StateSource::Ephemeral(config)
+            } else {
+                let state = resolve_or_exit(
+                    &cmd.state,
+                    dir,
+                    paths::STATE_FILE,
+                    "--state",
+                );
+                if let Some(config) =
+                    paths::resolve(&cmd.config, dir, paths::CONFIG_FILE)
+                {
+                    check_first_run(&config, Some(&state));
+                }
+                StateSource::File(state)
+            };
+            let config =
+                paths::resolve_optional(&cmd.config, dir, paths::CONFIG_FILE);
+
+            match &cmd.command {
+                BankCmds::List(bankcmd) => {
+                    let catalog =
+                        paths::resolve(&None, dir, paths::INSTITUTIONS_FILE);
+                    do_bank_list(bankcmd, &source, &catalog).await;
                 }
-                BankAccountCmds::Balance(balancecmd) => {
-                    do_bank_account_balance(
-                        &balancecmd,
-                        &cmd.state,
+                BankCmds::Authorize(bankcmd) => {
+                    let bank = match &bankcmd.bank {
+                        Some(_) => Some(bank_source_or_exit(
+                            cmd,
+                            dir,
+                            &bankcmd.auth,
+                            &bankcmd.bank,
+                        )),
+                        None => {
+                            paths::resolve(&bankcmd.auth, dir, paths::BANK_FILE)
+                                .map(BankSource::File)
+                        }
+                    };
+                    do_bank_authorization(bankcmd, &source, bank).await;
+                }
+                BankCmds::Show(bankcmd) => {
+                    do_bank_show(bankcmd, &source).await;
+                }
+                BankCmds::Capabilities(bankcmd) => {
+                    do_bank_capabilities(bankcmd, &source).await;
+                }
+                BankCmds::Status(statuscmd) => {
+                    let bank = bank_source_or_exit(
+                        cmd,
+                        dir,
+                        &statuscmd.auth,
+                        &statuscmd.bank,
+                    );
+                    do_bank_status(&source, &bank).await;
+                }
+                BankCmds::Requisition(reqcmd) => match &reqcmd.command {
+                    BankRequisitionCmds::Link(linkcmd) => {
+                        do_bank_requisition_link(linkcmd, &source).await;
+                    }
+                    BankRequisitionCmds::Accounts(accntcmd) => {
+                        do_bank_requisition_accounts(accntcmd, &source).await;
+                    }
+                    BankRequisitionCmds::List(_) => {
+                        do_bank_requisition_list(&source).await;
+                    }
+                    BankRequisitionCmds::Show(showcmd) => {
+                        do_bank_requisition_show(showcmd, &source).await;
+                    }
+                    BankRequisitionCmds::Delete(delcmd) => {
+                        do_bank_requisition_delete(delcmd, &source).await;
+                    }
+                },
+                BankCmds::Agreement(agreecmd) => match &agreecmd.command {
+                    BankAgreementCmds::Create(createcmd) => {
+                        do_bank_agreement_create(createcmd, &source).await;
+                    }
+                    BankAgreementCmds::List(_) => {
+                        do_bank_agreement_list(&source).await;
+                    }
+                    BankAgreementCmds::Show(showcmd) => {
+                        do_bank_agreement_show(showcmd, &source).await;
+                    }
+                    BankAgreementCmds::Delete(delcmd) => {
+                        do_bank_agreement_delete(delcmd, &source).await;
+                    }
+                },
+                BankCmds::Account(accntcmd) => {
+                    let bank = bank_source_or_exit(
+                        cmd,
+                        dir,
                         &accntcmd.auth,
-                    )
-                    .await;
+                        &accntcmd.bank,
+                    );
+                    match &accntcmd.command {
+                        BankAccountCmds::List(listcmd) => {
+                            do_bank_account_list(listcmd, &source, &bank).await;
+                        }
+                        BankAccountCmds::Transactions(txcmd) => {
+                            let exports =
+                                paths::resolve(&None, dir, paths::EXPORTS_FILE);
+                            if let Some(path) =
+                                paths::resolve(&None, dir, paths::QUOTA_FILE)
+                            {
+                                if let Err(err) = quota::load(&path) {
+                                    eprintln!("{}", err);
+                                }
+                            }
+                            do_bank_account_transactions(
+                                txcmd, &source, &bank, &config, &exports,
+                            )
+                            .await;
+                        }
+                        BankAccountCmds::Balance(balancecmd) => {
+                            do_bank_account_balance(
+                                balancecmd, &source, &bank, &config,
+                            )
+                            .await;
+                        }
+                        BankAccountCmds::Show(showcmd) => {
+                            do_bank_account_show(showcmd, &source, &bank).await;
+                        }
+                        BankAccountCmds::Details(detailscmd) => {
+                            do_bank_account_details(detailscmd, &source, &bank)
+                                .await;
+                        }
+                    }
                 }
+                BankCmds::Add(_)
+                | BankCmds::Remove(_)
+                | BankCmds::Rename(_) => {
+                    // Handled above, before any token is needed.
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{banks, BankAccountTransactionsCmd, Cli, Commands, Secrets};
+    use super::{chunk_transactions, chunks, count_shown, replace_secrets};
+    use super::{create_private_file, redirect_address, write_atomically};
+    use crate::cli::{BankAccountCmds, BankCmds};
+
+    const SECRETS: Secrets = Secrets {
+        secret_id: "new-id",
+        secret_key: "new-key",
+    };
+
+    fn parse(contents: &str) -> toml::Value {
+        toml::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn replace_secrets_escapes_values() {
+        let secrets = Secrets {
+            secret_id: "id with \"quotes\"",
+            secret_key: "back\\slash",
+        };
+        let out = replace_secrets("secret_id = \"old\"\n", &secrets);
+        let value = parse(&out);
+        assert_eq!(value["secret_id"].as_str(), Some(secrets.secret_id));
+        assert_eq!(value["secret_key"].as_str(), Some(secrets.secret_key));
+    }
+
+    #[test]
+    fn replace_secrets_adds_missing_keys_before_tables() {
+        let contents = "[bank]\nsecret_id = \"kept\"\n";
+        let out = replace_secrets(contents, &SECRETS);
+        assert!(out.starts_with("secret_id = \"new-id\"\n"));
+        let value = parse(&out);
+        assert_eq!(value["secret_id"].as_str(), Some("new-id"));
+        assert_eq!(value["secret_key"].as_str(), Some("new-key"));
+        assert_eq!(value["bank"]["secret_id"].as_str(), Some("kept"));
+    }
+
+    #[test]
+    fn replace_secrets_keeps_comments_and_settings() {
+        let contents = "# credentials\n\
+            secret_id = \"old-id\"\n\
+            # secret_key = \"commented\"\n\
+            secret_key = \"old-key\"\n\
+            \n\
+            [profiles.work]\n\
+            secret_key = \"work-key\"\n";
+        let out = replace_secrets(contents, &SECRETS);
+        assert_eq!(
+            out,
+            "# credentials\n\
+            secret_id = \"new-id\"\n\
+            # secret_key = \"commented\"\n\
+            secret_key = \"new-key\"\n\
+            \n\
+            [profiles.work]\n\
+            secret_key = \"work-key\"\n"
+        );
+    }
+
+    fn transactions_cmd(args: &[&str]) -> BankAccountTransactionsCmd {
+        let base = ["nordigen-cli", "bank", "account", "transactions"];
+        let cli = Cli::try_parse_from(base.iter().chain(args)).unwrap();
+        let bankcmd = match cli.command {
+            Some(Commands::Bank(cmd)) => cmd.command,
+            _ => unreachable!(),
+        };
+        match bankcmd {
+            BankCmds::Account(cmd) => match cmd.command {
+                BankAccountCmds::Transactions(cmd) => cmd,
+                _ => unreachable!(),
             },
-        },
+            _ => unreachable!(),
+        }
+    }
+
+    fn account() -> banks::Account {
+        serde_json::from_value(serde_json::json!({
+            "meta": {"id": "account", "institution_id": "BANK"},
+            "details": {},
+        }))
+        .unwrap()
+    }
+
+    /// What the bank returns for a window starting on `from`: what was
+    /// booked in it, and the same pending transaction as ever.
+    fn transactions(from: chrono::NaiveDate) -> banks::Transactions {
+        serde_json::from_value(serde_json::json!({
+            "booked": [{
+                "transactionId": from.to_string(),
+                "bookingDate": from,
+                "transactionAmount": {"amount": "-1.00", "currency": "EUR"},
+            }],
+            "pending": [{
+                "transactionAmount": {"amount": "-2.00", "currency": "EUR"},
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn chunks_include_pending_transactions_once() {
+        let cmd = transactions_cmd(&[
+            "--format",
+            "csv",
+            "--from",
+            "2023-01-01",
+            "--to",
+            "2023-03-31",
+            "--chunk",
+            "monthly",
+            "--output",
+            "out.csv",
+        ]);
+        let account = account();
+        let windows = chunks::windows(
+            cmd.from.unwrap(),
+            cmd.to.unwrap(),
+            cmd.chunk.unwrap(),
+        );
+        assert_eq!(windows.len(), 3);
+
+        let (mut booked, mut pending, mut found) = (0, 0, 0);
+        for (idx, window) in windows.iter().enumerate() {
+            let fetched = vec![(&account, transactions(window.from))];
+            let last = idx + 1 == windows.len();
+            let groups = chunk_transactions(&fetched, &cmd, last);
+            booked += groups[0].booked.len();
+            pending += groups[0].pending.len();
+            found += count_shown(&groups, &cmd);
+            assert_eq!(groups[0].pending.len(), usize::from(last));
+        }
+        assert_eq!((booked, pending, found), (3, 1, 4));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomically_keeps_files_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir()
+            .join(format!("nordigen-cli-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mode = |path: &std::path::PathBuf| {
+            std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+        };
+
+        let new = dir.join("new.toml");
+        let _ = std::fs::remove_file(&new);
+        write_atomically(&new, "secret_id = \"id\"\n").unwrap();
+        assert_eq!(mode(&new), 0o600);
+
+        let old = dir.join("old.toml");
+        std::fs::write(&old, "old").unwrap();
+        let perms = std::fs::Permissions::from_mode(0o640);
+        std::fs::set_permissions(&old, perms).unwrap();
+        write_atomically(&old, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&old).unwrap(), "new");
+        assert_eq!(mode(&old), 0o640);
+
+        let left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension() == Some("tmp".as_ref()))
+            .collect();
+        assert!(left.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_private_file_restricts_existing_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir()
+            .join(format!("nordigen-cli-private-{}", std::process::id()));
+        std::fs::write(&path, "public").unwrap();
+        let perms = std::fs::Permissions::from_mode(0o644);
+        std::fs::set_permissions(&path, perms).unwrap();
+
+        drop(create_private_file(&path).unwrap());
+        let meta = std::fs::metadata(&path).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(meta.len(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reauthorizing_listens_where_the_bank_last_redirected() {
+        let state = |redirect: &str| -> banks::BankAuthState {
+            let json = format!(
+                r#"{{"requisition": {{"requisition_id": "req"}}{}}}"#,
+                redirect
+            );
+            serde_json::from_str(&json).unwrap()
+        };
+
+        let last = state(r#", "redirect": "http://0.0.0.0:8080/""#);
+        assert_eq!(redirect_address(&last), (String::from("0.0.0.0"), 8080));
+
+        let defaults = (banks::DEFAULT_HOST.to_string(), banks::DEFAULT_PORT);
+        assert_eq!(redirect_address(&state("")), defaults);
+        let bogus = state(r#", "redirect": "not a url""#);
+        assert_eq!(redirect_address(&bogus), defaults);
     }
 }
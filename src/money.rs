@@ -0,0 +1,175 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use serde::{Serialize, Serializer};
+
+/// Decimal places kept; no ISO 4217 currency uses more than four.
+const SCALE: usize = 4;
+const ONE: i64 = 10_000;
+
+/// A monetary amount, kept in ten-thousandths of its currency's unit so
+/// that sums are exact; shown with at least two decimal places.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount(i64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(&self) -> Amount {
+        Amount(self.0.abs())
+    }
+
+    /// The next whole unit up, or the amount itself if already whole.
+    pub fn ceil(&self) -> Amount {
+        let rem = self.0.rem_euclid(ONE);
+        if rem == 0 {
+            *self
+        } else {
+            Amount(self.0 - rem + ONE)
+        }
+    }
+}
+
+impl std::str::FromStr for Amount {
+    type Err = String;
+
+    /// Parse a decimal amount such as `-12.30`, as Nordigen reports them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid amount '{}'", s);
+        let text = s.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if (whole.is_empty() && frac.is_empty())
+            || frac.len() > SCALE
+            || !whole
+                .chars()
+                .chain(frac.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole: i64 = match whole {
+            "" => 0,
+            _ => whole.parse().map_err(|_| invalid())?,
+        };
+        let frac: i64 = match frac {
+            "" => 0,
+            _ => format!("{:0<width$}", frac, width = SCALE)
+                .parse()
+                .map_err(|_| invalid())?,
+        };
+        let units = whole
+            .checked_mul(ONE)
+            .and_then(|units| units.checked_add(frac))
+            .ok_or_else(invalid)?;
+        Ok(Amount(if negative { -units } else { units }))
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let units = self.0.unsigned_abs();
+        let one = ONE as u64;
+        let frac = format!("{:0width$}", units % one, width = SCALE);
+        let frac = frac.trim_end_matches('0');
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let text = format!("{}{}.{:0<2}", sign, units / one, frac);
+        f.pad(&text)
+    }
+}
+
+/// Amounts are serialized as decimal strings, as Nordigen sends them,
+/// rather than as lossy floats.
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::ops::Add for Amount {
+    type Output = Amount;
+
+    fn add(self, other: Amount) -> Amount {
+        Amount(self.0 + other.0)
+    }
+}
+
+impl std::ops::AddAssign for Amount {
+    fn add_assign(&mut self, other: Amount) {
+        self.0 += other.0;
+    }
+}
+
+impl std::ops::Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, other: Amount) -> Amount {
+        Amount(self.0 - other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Amount;
+
+    fn amount(text: &str) -> Amount {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_shows_amounts() {
+        assert_eq!(amount("-12.3").to_string(), "-12.30");
+        assert_eq!(amount("+5").to_string(), "5.00");
+        assert_eq!(amount("0.125").to_string(), "0.125");
+        assert_eq!(amount("1000.0000").to_string(), "1000.00");
+        assert_eq!(amount("-0.05").to_string(), "-0.05");
+        assert_eq!(amount(" 7.10 ").to_string(), "7.10");
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_amount() {
+        for text in ["", "-", ".", "1.2.3", "1e3", "12,30", "0.00001", "abc"] {
+            assert!(text.parse::<Amount>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn sums_are_exact() {
+        let total = ["0.1", "0.2", "-0.3"]
+            .iter()
+            .fold(Amount::ZERO, |acc, text| acc + amount(text));
+        assert_eq!(total, Amount::ZERO);
+
+        let debit = amount("-12.1").abs();
+        assert_eq!((debit.ceil() - debit).to_string(), "0.90");
+    }
+
+    #[test]
+    fn ceil_rounds_up_to_whole_units() {
+        assert_eq!(amount("12.01").ceil(), amount("13"));
+        assert_eq!(amount("12").ceil(), amount("12"));
+        assert_eq!(amount("-12.5").ceil(), amount("-12"));
+    }
+
+    #[test]
+    fn serializes_as_a_string() {
+        let value = serde_json::to_value(amount("-3.5")).unwrap();
+        assert_eq!(value, serde_json::json!("-3.50"));
+    }
+}
@@ -0,0 +1,131 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static PROGRESS_JSON: AtomicBool = AtomicBool::new(false);
+
+/// How progress of long operations is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// One JSON object per line on stderr.
+    Json,
+}
+
+impl std::str::FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!("unknown progress format '{}'; try json", s)),
+        }
+    }
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn set_progress(format: Option<ProgressFormat>) {
+    PROGRESS_JSON
+        .store(format == Some(ProgressFormat::Json), Ordering::Relaxed);
+}
+
+fn emit(event: serde_json::Value) {
+    if PROGRESS_JSON.load(Ordering::Relaxed) {
+        eprintln!("{}", event);
+    }
+}
+
+/// Whether to draw progress bars: only on a terminal, and neither when
+/// running quietly nor when reporting progress as JSON.
+fn draws_bars() -> bool {
+    !is_quiet()
+        && !PROGRESS_JSON.load(Ordering::Relaxed)
+        && std::io::stderr().is_terminal()
+}
+
+/// A step of a long operation, reported as `started`, `progress` and
+/// `finished` events when running with `--progress json`, or drawn on
+/// stderr as a spinner, then a bar once its total is known.
+pub struct Progress {
+    step: String,
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    pub fn start(step: &str) -> Progress {
+        emit(serde_json::json!({ "event": "started", "step": step }));
+        let bar = draws_bars().then(|| {
+            let bar = ProgressBar::new_spinner();
+            if let Ok(style) = ProgressStyle::with_template("{spinner} {msg}") {
+                bar.set_style(style);
+            }
+            bar.set_message(step.to_string());
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        });
+        Progress {
+            step: step.to_string(),
+            bar,
+        }
+    }
+
+    pub fn advance(&self, done: usize, total: usize) {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        emit(serde_json::json!({
+            "event": "progress",
+            "step": self.step,
+            "done": done,
+            "total": total,
+            "percent": percent,
+        }));
+        if let Some(bar) = &self.bar {
+            if bar.length().is_none() {
+                if let Ok(style) = ProgressStyle::with_template(
+                    "{spinner} {msg} [{bar:30}] {pos}/{len}",
+                ) {
+                    bar.set_style(style.progress_chars("=> "));
+                }
+            }
+            bar.set_length(total as u64);
+            bar.set_position(done as u64);
+        }
+    }
+
+    pub fn finish(self, ok: bool) {
+        emit(serde_json::json!({
+            "event": "finished",
+            "step": self.step,
+            "ok": ok,
+        }));
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// Print an informational message to stderr, unless running quietly.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::msg::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
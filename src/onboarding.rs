@@ -0,0 +1,70 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::path::Path;
+
+use crate::paths;
+
+const SECRETS_URL: &str = "https://ob.nordigen.com/user-secrets/";
+
+/// What a first run is missing, in the order it needs setting up.
+pub enum Missing<'a> {
+    Config(&'a Path),
+    State { config: &'a Path, state: &'a Path },
+}
+
+/// ` --<option> <path>`, unless `path` is where `name` is looked for by
+/// default, so suggested commands work as typed.
+fn flag(path: &Path, name: &str, option: &str) -> String {
+    match paths::default_path(name) {
+        Some(default) if default == path => String::new(),
+        _ => format!(" {} {}", option, path.display()),
+    }
+}
+
+/// Next steps to get from `missing` to a working setup.
+pub fn guide(missing: &Missing) -> String {
+    let (intro, steps) = match missing {
+        Missing::Config(config) => {
+            let init = format!(
+                "nordigen-cli init{} --secret-id <ID>",
+                flag(config, paths::CONFIG_FILE, "--config")
+            );
+            (
+                tr!(FirstRunConfig, config.display()),
+                vec![
+                    tr!(FirstRunSecrets, SECRETS_URL),
+                    tr!(FirstRunInit, init),
+                ],
+            )
+        }
+        Missing::State { config, state } => {
+            let authorize = format!(
+                "nordigen-cli authorize{}{}",
+                flag(config, paths::CONFIG_FILE, "--config"),
+                flag(state, paths::STATE_FILE, "--state")
+            );
+            (
+                tr!(FirstRunState, state.display()),
+                vec![tr!(FirstRunAuthorize, authorize)],
+            )
+        }
+    };
+
+    let mut out = intro;
+    for (n, step) in steps.iter().enumerate() {
+        out.push_str(&format!("\n  {}. {}", n + 1, step));
+    }
+    out
+}
+
+/// Print the next steps for `missing` and exit.
+pub fn exit(missing: &Missing) -> ! {
+    eprintln!("{}", guide(missing));
+    std::process::exit(1);
+}
@@ -0,0 +1,81 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::path::PathBuf;
+
+pub const CONFIG_FILE: &str = "config.toml";
+pub const STATE_FILE: &str = "state.json";
+pub const BANK_FILE: &str = "bank.json";
+pub const BANKS_FILE: &str = "banks.json";
+pub const EXPORTS_FILE: &str = "exports.json";
+pub const INSTITUTIONS_FILE: &str = "institutions.json";
+pub const QUOTA_FILE: &str = "quota.json";
+
+const APP_DIR: &str = "nordigen-cli";
+
+/// `$<var>`, or `fallback` inside the home directory, as per the XDG base
+/// directory specification; relative paths are ignored.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    if let Some(value) = std::env::var_os(var) {
+        let dir = PathBuf::from(value);
+        if dir.is_absolute() {
+            return Some(dir);
+        }
+    }
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    Some(home.join(fallback))
+}
+
+/// Where `name` lives when neither its option nor `--config-dir` is given:
+/// the config file under `~/.config/nordigen-cli`, and the rest, being
+/// written by us, under `~/.local/share/nordigen-cli`.
+pub fn default_path(name: &str) -> Option<PathBuf> {
+    let dir = if name == CONFIG_FILE {
+        xdg_dir("XDG_CONFIG_HOME", ".config")?
+    } else {
+        xdg_dir("XDG_DATA_HOME", ".local/share")?
+    };
+    Some(dir.join(APP_DIR).join(name))
+}
+
+/// Where profiles live, one directory each, holding what `--config-dir`
+/// would: `~/.config/nordigen-cli/profiles`.
+pub fn profiles_dir() -> Option<PathBuf> {
+    Some(
+        xdg_dir("XDG_CONFIG_HOME", ".config")?
+            .join(APP_DIR)
+            .join("profiles"),
+    )
+}
+
+/// Use `path` if given, otherwise `name` inside the config directory, or
+/// the XDG default if there's no config directory either.
+pub fn resolve(
+    path: &Option<PathBuf>,
+    dir: &Option<PathBuf>,
+    name: &str,
+) -> Option<PathBuf> {
+    match (path, dir) {
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(dir)) => Some(dir.join(name)),
+        (None, None) => default_path(name),
+    }
+}
+
+/// As `resolve`, for files that may be absent: the default copy is only
+/// used if it exists.
+pub fn resolve_optional(
+    path: &Option<PathBuf>,
+    dir: &Option<PathBuf>,
+    name: &str,
+) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(path.clone()),
+        None => resolve(path, dir, name).filter(|p| p.exists()),
+    }
+}
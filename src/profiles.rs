@@ -0,0 +1,84 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::path::PathBuf;
+
+use crate::paths;
+
+fn base_dir() -> Result<PathBuf, String> {
+    paths::profiles_dir()
+        .ok_or_else(|| String::from("Unable to find the home directory."))
+}
+
+/// Profile names become directory names, so keep them to letters, digits,
+/// `-` and `_`.
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "Invalid profile name '{}'; use letters, digits, - and _.",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Directory of an existing profile.
+pub fn dir(name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    let dir = base_dir()?.join(name);
+    if !dir.is_dir() {
+        return Err(format!(
+            "No profile '{}'; create it with `nordigen-cli profile create {}`.",
+            name, name
+        ));
+    }
+    Ok(dir)
+}
+
+/// Names of every profile, sorted.
+pub fn list() -> Result<Vec<String>, String> {
+    let base = base_dir()?;
+    if !base.exists() {
+        return Ok(vec![]);
+    }
+    let entries = std::fs::read_dir(&base)
+        .map_err(|err| format!("Unable to read {}: {}", base.display(), err))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn create(name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    let dir = base_dir()?.join(name);
+    if dir.exists() {
+        return Err(format!("Profile '{}' already exists.", name));
+    }
+    std::fs::create_dir_all(&dir).map_err(|err| {
+        format!("Unable to create {}: {}", dir.display(), err)
+    })?;
+    Ok(dir)
+}
+
+/// Remove a profile's directory, along with its config, state and bank
+/// authorizations.
+pub fn delete(name: &str) -> Result<PathBuf, String> {
+    let dir = dir(name)?;
+    std::fs::remove_dir_all(&dir).map_err(|err| {
+        format!("Unable to delete {}: {}", dir.display(), err)
+    })?;
+    Ok(dir)
+}
@@ -0,0 +1,128 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use nordigen_cli::banks::{Transaction, Transactions};
+use serde::Deserialize;
+
+/// Known workarounds, shipped with the binary.
+const BUNDLED: &str = include_str!("quirks.toml");
+
+/// Workarounds for one bank; those not set are off. See quirks.toml.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Quirk {
+    pub max_days: Option<u32>,
+    pub ignores_dates: Option<bool>,
+    pub value_date_for_booking: Option<bool>,
+    pub decimal_comma: Option<bool>,
+    pub inverted_amounts: Option<bool>,
+}
+
+impl Quirk {
+    /// `other`'s fields, falling back to ours where it has none.
+    fn merge(&self, other: &Quirk) -> Quirk {
+        Quirk {
+            max_days: other.max_days.or(self.max_days),
+            ignores_dates: other.ignores_dates.or(self.ignores_dates),
+            value_date_for_booking: other
+                .value_date_for_booking
+                .or(self.value_date_for_booking),
+            decimal_comma: other.decimal_comma.or(self.decimal_comma),
+            inverted_amounts: other.inverted_amounts.or(self.inverted_amounts),
+        }
+    }
+
+    /// Date ranges to request transactions for, so that none spans more
+    /// than `max_days`. Without a start date, there's nothing to split.
+    pub fn ranges(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Vec<(Option<NaiveDate>, Option<NaiveDate>)> {
+        let (from, days) = match (from, self.max_days) {
+            (Some(from), Some(days)) if days > 0 => (from, days as i64),
+            _ => return vec![(from, to)],
+        };
+        let to = to.unwrap_or_else(|| chrono::Local::now().date_naive());
+        let mut res = vec![];
+        let mut start = from;
+        while start <= to {
+            let end = (start + chrono::Duration::days(days - 1)).min(to);
+            res.push((Some(start), Some(end)));
+            start = end + chrono::Duration::days(1);
+        }
+        res
+    }
+
+    fn fix_amount(&self, tx: &mut Transaction) {
+        let amount = &mut tx.transaction_amount.amount;
+        if self.decimal_comma == Some(true) {
+            *amount = amount.replace('.', "").replace(',', ".");
+        }
+        if self.inverted_amounts == Some(true) {
+            *amount = match amount.trim().strip_prefix('-') {
+                Some(rest) => rest.to_string(),
+                None => format!("-{}", amount.trim()),
+            };
+        }
+    }
+
+    /// Apply the workarounds to transactions requested for `from` to `to`.
+    pub fn fix(
+        &self,
+        txns: &mut Transactions,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) {
+        for tx in txns.booked.iter_mut().chain(txns.pending.iter_mut()) {
+            if self.value_date_for_booking == Some(true) {
+                tx.booking_date = tx.booking_date.or(tx.value_date);
+            }
+            self.fix_amount(tx);
+        }
+        if self.ignores_dates == Some(true) {
+            txns.booked
+                .retain(|tx| match tx.booking_date.or(tx.value_date) {
+                    None => true,
+                    Some(date) => {
+                        from.is_none_or(|from| date >= from)
+                            && to.is_none_or(|to| date <= to)
+                    }
+                });
+        }
+    }
+}
+
+/// Workarounds by institution id: the bundled ones, overridden by those in
+/// the config file.
+pub struct Quirks {
+    banks: HashMap<String, Quirk>,
+}
+
+impl Quirks {
+    pub fn load(overrides: &HashMap<String, Quirk>) -> Result<Quirks, String> {
+        let mut banks: HashMap<String, Quirk> = match toml::from_str(BUNDLED) {
+            Err(err) => {
+                return Err(format!("Unable to parse bundled quirks: {}", err));
+            }
+            Ok(res) => res,
+        };
+        for (id, quirk) in overrides {
+            let merged =
+                banks.get(id).cloned().unwrap_or_default().merge(quirk);
+            banks.insert(id.clone(), merged);
+        }
+        Ok(Quirks { banks })
+    }
+
+    pub fn get(&self, institution_id: &str) -> Quirk {
+        self.banks.get(institution_id).cloned().unwrap_or_default()
+    }
+}
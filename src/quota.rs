@@ -0,0 +1,89 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use nordigen_cli::banks::Account;
+use nordigen_cli::Quota;
+
+use crate::guard;
+
+/// Where account quotas are kept across runs, quota.json, once loaded.
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Start from the quotas kept in `path`, and keep them there on `save`.
+pub fn load(path: &PathBuf) -> Result<(), String> {
+    *PATH.lock().unwrap() = Some(path.clone());
+    if !path.exists() {
+        return Ok(());
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+    let known: BTreeMap<String, Quota> = serde_json::from_str(&contents)
+        .map_err(|err| {
+            format!("Unable to parse {}: {}", path.display(), err)
+        })?;
+    nordigen_cli::add_quotas(known);
+    Ok(())
+}
+
+/// Keep the quotas seen so far, if loaded and not in read-only mode;
+/// failing to is only warned about.
+pub fn save() {
+    let path = match PATH.lock().unwrap().as_ref() {
+        None => return,
+        Some(path) => path.clone(),
+    };
+    if guard::is_read_only() {
+        return;
+    }
+    let res = serde_json::to_string_pretty(&nordigen_cli::quotas())
+        .map_err(|err| err.to_string())
+        .and_then(|val| {
+            // the data directory may not have been created yet
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| err.to_string())?;
+            }
+            std::fs::write(&path, val).map_err(|err| err.to_string())
+        });
+    if let Err(err) = res {
+        eprintln!("Unable to keep quotas in {}: {}", path.display(), err);
+    }
+}
+
+/// An account whose known quota won't cover the requests about to be made.
+pub struct Shortfall<'a> {
+    pub account: &'a Account,
+    pub needed: usize,
+    pub quota: Quota,
+}
+
+/// Accounts, out of `needed` calls of `kind` each, that are known to have
+/// fewer left in their daily quota.
+pub fn shortfalls<'a>(
+    needed: &[(&'a Account, usize)],
+    kind: &str,
+) -> Vec<Shortfall<'a>> {
+    needed
+        .iter()
+        .filter_map(|(account, needed)| {
+            let quota = nordigen_cli::quota(&account.meta.id, kind)?;
+            if (quota.remaining as usize) >= *needed {
+                return None;
+            }
+            Some(Shortfall {
+                account,
+                needed: *needed,
+                quota,
+            })
+        })
+        .collect()
+}
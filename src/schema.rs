@@ -0,0 +1,32 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use nordigen_cli::banks;
+
+/// Models whose JSON we output, by the name `schema` knows them as.
+pub const MODELS: &[&str] = &[
+    "account",
+    "details",
+    "balance",
+    "transactions",
+    "institution",
+];
+
+pub fn schema(model: &str) -> Option<RootSchema> {
+    match model {
+        "account" => Some(schema_for!(banks::Account)),
+        "details" => Some(schema_for!(banks::AccountDetails)),
+        "balance" => Some(schema_for!(banks::Balance)),
+        "transactions" => Some(schema_for!(banks::Transactions)),
+        "institution" => Some(schema_for!(banks::Institution)),
+        _ => None,
+    }
+}
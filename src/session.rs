@@ -0,0 +1,96 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use nordigen::authorize;
+use nordigen::state::NordigenState;
+
+static REFRESH_MARGIN: AtomicU64 = AtomicU64::new(0);
+
+/// Treat access tokens expiring within `secs` as already expired, so they
+/// don't run out halfway through a command.
+pub fn set_refresh_margin(secs: u64) {
+    REFRESH_MARGIN.store(secs, Ordering::Relaxed);
+}
+
+/// Hands out access tokens, transparently refreshing an expired one with
+/// the refresh token. Refreshed state is written back to `path`, if any;
+/// otherwise it lasts only as long as the session.
+pub struct SessionManager {
+    state: NordigenState,
+    path: Option<std::path::PathBuf>,
+}
+
+impl SessionManager {
+    pub fn new(
+        state: NordigenState,
+        path: Option<std::path::PathBuf>,
+    ) -> SessionManager {
+        SessionManager { state, path }
+    }
+
+    pub fn state(&self) -> &NordigenState {
+        &self.state
+    }
+
+    /// Whether the access token has expired, or will within the margin.
+    pub fn expires_soon(&self) -> bool {
+        let margin = REFRESH_MARGIN.load(Ordering::Relaxed) as i64;
+        let remaining = self.state.token_expires_on().timestamp()
+            - chrono::Utc::now().timestamp();
+        self.state.is_token_expired() || remaining < margin
+    }
+
+    /// Obtain a new access token, regardless of whether the current one
+    /// has expired.
+    pub async fn refresh(&mut self) -> Result<(), String> {
+        if self.state.is_refresh_expired() {
+            return Err(tr!(RefreshExpiredAuthorize));
+        }
+
+        let (token, expires) =
+            match authorize::refresh(&self.state.refresh_token).await {
+                Err(err) => {
+                    return Err(format!("Error refreshing token: {}", err));
+                }
+                Ok(res) => res,
+            };
+
+        tracing::debug!("refreshed access token, expires in {}s", expires);
+        let refresh = self.state.refresh_token.clone();
+        let refresh_expires = self.state.refresh_expires;
+        self.state = match &self.path {
+            None => {
+                NordigenState::new(token, expires, refresh, refresh_expires)
+            }
+            Some(path) => match crate::write_state(
+                path,
+                token,
+                refresh,
+                expires,
+                refresh_expires,
+            ) {
+                Err(err) => {
+                    return Err(format!("Unable to write state: {}", err));
+                }
+                Ok(res) => res,
+            },
+        };
+        Ok(())
+    }
+
+    /// A valid access token, refreshing first if needed.
+    pub async fn token(&mut self) -> Result<String, String> {
+        if self.expires_soon() {
+            tracing::info!("access token expired or about to, refreshing");
+            self.refresh().await?;
+        }
+        Ok(self.state.token.clone())
+    }
+}
@@ -0,0 +1,194 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use nordigen_cli::banks::Account;
+
+use crate::ledger;
+use crate::quirks::Quirk;
+
+/// Balance types considered to be "the" balance of an account, in order of
+/// preference, when none have been configured.
+const DEFAULT_BALANCE_TYPES: &[&str] = &[
+    "interimAvailable",
+    "expected",
+    "closingBooked",
+    "interimBooked",
+    "openingBooked",
+];
+
+#[derive(Deserialize, Default)]
+pub struct BalanceSettings {
+    /// Preferred balance types, in order
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct AccountSettings {
+    /// Short name for the account, to list it by in `[groups]`
+    pub alias: Option<String>,
+    /// Preferred balance types for this account, in order
+    #[serde(default)]
+    pub balance_types: Vec<String>,
+}
+
+/// Account names for ledger and beancount exports.
+#[derive(Deserialize)]
+pub struct LedgerSettings {
+    /// Ledger account of each bank account, keyed by IBAN, masked PAN, or
+    /// account id
+    #[serde(default)]
+    pub accounts: HashMap<String, String>,
+    /// Counter account of transactions, keyed by counterparty label
+    #[serde(default)]
+    pub counterparties: HashMap<String, String>,
+    /// Counter account of transactions, keyed by contact category
+    #[serde(default)]
+    pub categories: HashMap<String, String>,
+    #[serde(default = "default_expenses")]
+    pub expenses: String,
+    #[serde(default = "default_income")]
+    pub income: String,
+}
+
+fn default_expenses() -> String {
+    String::from("Expenses:Unknown")
+}
+
+fn default_income() -> String {
+    String::from("Income:Unknown")
+}
+
+impl Default for LedgerSettings {
+    fn default() -> Self {
+        LedgerSettings {
+            accounts: HashMap::new(),
+            counterparties: HashMap::new(),
+            categories: HashMap::new(),
+            expenses: default_expenses(),
+            income: default_income(),
+        }
+    }
+}
+
+impl LedgerSettings {
+    /// Ledger account for `account`, defaulting to one under
+    /// `Assets:Bank` named after it.
+    pub fn account(&self, account: &Account) -> String {
+        self.accounts
+            .get(&account.identifier())
+            .or_else(|| self.accounts.get(&account.meta.id))
+            .cloned()
+            .unwrap_or_else(|| {
+                format!(
+                    "Assets:Bank:{}",
+                    ledger::component(&account.identifier())
+                )
+            })
+    }
+
+    /// Counter account for a transaction with the counterparty `label` and
+    /// contact `category`, if any.
+    pub fn counter_account(
+        &self,
+        label: &str,
+        category: Option<&str>,
+        is_debit: bool,
+    ) -> String {
+        if let Some(res) = self.counterparties.get(label) {
+            return res.clone();
+        }
+        if let Some(res) = category.and_then(|c| self.categories.get(c)) {
+            return res.clone();
+        }
+        match is_debit {
+            true => self.expenses.clone(),
+            false => self.income.clone(),
+        }
+    }
+}
+
+/// CLI settings, read from the optional sections of the config file.
+///
+/// Accounts are keyed by IBAN, masked PAN, or account id.
+#[derive(Deserialize, Default)]
+pub struct Settings {
+    /// Refuse to create or delete remote resources, or to write auth
+    /// state, as with `--read-only`
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub balance: BalanceSettings,
+    #[serde(default)]
+    pub accounts: HashMap<String, AccountSettings>,
+    #[serde(default)]
+    pub ledger: LedgerSettings,
+    /// Accounts selected by `--group`, listed by alias, IBAN, masked PAN
+    /// or account id
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+    /// Workarounds by institution id, over the bundled ones
+    #[serde(default)]
+    pub quirks: HashMap<String, Quirk>,
+}
+
+impl Settings {
+    pub fn account(&self, account: &Account) -> Option<&AccountSettings> {
+        self.accounts
+            .get(&account.identifier())
+            .or_else(|| self.accounts.get(&account.meta.id))
+    }
+
+    /// Members of the group `name`.
+    pub fn group(&self, name: &str) -> Result<&[String], String> {
+        if let Some(members) = self.groups.get(name) {
+            return Ok(members);
+        }
+        if self.groups.is_empty() {
+            return Err(format!("No group '{}'; none are configured.", name));
+        }
+        let mut names: Vec<&str> =
+            self.groups.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        Err(format!(
+            "No group '{}'; known are: {}",
+            name,
+            names.join(", ")
+        ))
+    }
+
+    /// Whether `account` is among `members`, as listed in a group.
+    pub fn is_member(&self, members: &[String], account: &Account) -> bool {
+        let alias = self.account(account).and_then(|a| a.alias.as_ref());
+        members.iter().any(|m| {
+            *m == account.identifier()
+                || *m == account.meta.id
+                || Some(m) == alias
+        })
+    }
+
+    /// Balance types to look for on `account`, in order of preference.
+    pub fn balance_types(&self, account: &Account) -> Vec<String> {
+        if let Some(accnt) = self.account(account) {
+            if !accnt.balance_types.is_empty() {
+                return accnt.balance_types.clone();
+            }
+        }
+        if !self.balance.types.is_empty() {
+            return self.balance.types.clone();
+        }
+        DEFAULT_BALANCE_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use nordigen_cli::money::Amount;
+
+/// Aggregates over a set of transaction amounts.
+#[derive(Default, Debug)]
+pub struct Summary {
+    pub count: usize,
+    pub debits: Amount,
+    pub credits: Amount,
+    /// What rounding each debit up to the next whole unit would have saved
+    pub round_up: Amount,
+}
+
+impl Summary {
+    pub fn add(&mut self, amount: Amount) {
+        self.count += 1;
+        if amount.is_negative() {
+            self.debits += amount;
+            self.round_up += amount.abs().ceil() - amount.abs();
+        } else {
+            self.credits += amount;
+        }
+    }
+
+    pub fn net(&self) -> Amount {
+        self.credits + self.debits
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "transactions: {}", self.count)?;
+        writeln!(f, "      debits: {}", self.debits)?;
+        writeln!(f, "     credits: {}", self.credits)?;
+        write!(f, "         net: {}", self.net())
+    }
+}
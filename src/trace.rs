@@ -0,0 +1,339 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Where HTTP exchanges are recorded, when tracing with `--trace-http`.
+static TRACE: Mutex<Option<std::fs::File>> = Mutex::new(None);
+
+const REDACTED: &str = "[REDACTED]";
+
+/// JSON keys whose values are always redacted, compared in lowercase with
+/// underscores removed.
+const SECRET_KEYS: &[&str] = &[
+    "access",
+    "refresh",
+    "token",
+    "secretid",
+    "secretkey",
+    "iban",
+    "bban",
+    "maskedpan",
+    "ownername",
+    "owneraddressunstructured",
+    "owneraddressstructured",
+    "creditorname",
+    "debtorname",
+    "ultimatecreditor",
+    "ultimatedebtor",
+    // free text, often naming the other party or their account number
+    "remittanceinformationunstructured",
+    "remittanceinformationunstructuredarray",
+];
+
+/// Headers whose values are always redacted, compared in lowercase.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+];
+
+/// Open `path` readable by us alone, even when replacing a file that
+/// wasn't: traces are redacted, but still tell of one's banking.
+fn create_private(path: &std::path::PathBuf) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    // an existing file keeps its mode when opened
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
+pub fn set_trace_file(path: &std::path::PathBuf) -> Result<(), String> {
+    let file = match create_private(path) {
+        Err(err) => {
+            return Err(format!(
+                "Unable to open trace file at {}: {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(res) => res,
+    };
+    *TRACE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+fn write(text: &str) {
+    if let Some(file) = TRACE.lock().unwrap().as_mut() {
+        let _ = file.write_all(text.as_bytes());
+    }
+}
+
+fn is_enabled() -> bool {
+    TRACE.lock().unwrap().is_some()
+}
+
+fn looks_like_iban(word: &str) -> bool {
+    let chars: Vec<char> = word.chars().collect();
+    chars.len() >= 15
+        && chars.len() <= 34
+        && chars[..2].iter().all(|c| c.is_ascii_uppercase())
+        && chars[2..4].iter().all(|c| c.is_ascii_digit())
+        && chars[4..]
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Replace anything shaped like an IBAN in free text.
+fn redact_ibans(text: &str) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if looks_like_iban(&word) {
+            out.push_str(REDACTED);
+        } else {
+            out.push_str(&word);
+        }
+        word.clear();
+        out.push(c);
+    }
+    out.pop();
+    out
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, entry) in map.iter_mut() {
+                let key = key.to_lowercase().replace('_', "");
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    *entry = serde_json::Value::from(REDACTED);
+                } else {
+                    redact_value(entry);
+                }
+            }
+        }
+        serde_json::Value::Array(entries) => {
+            entries.iter_mut().for_each(redact_value);
+        }
+        serde_json::Value::String(text) => {
+            *text = redact_ibans(text);
+        }
+        _ => {}
+    }
+}
+
+/// Redact tokens, secrets, account identifiers and names from a body,
+/// keeping its structure.
+pub fn redact(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Err(_) => redact_ibans(body),
+        Ok(mut value) => {
+            redact_value(&mut value);
+            serde_json::to_string_pretty(&value)
+                .unwrap_or_else(|_| redact_ibans(body))
+        }
+    }
+}
+
+fn prefixed(prefix: &str, text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{} {}\n", prefix, line))
+        .collect()
+}
+
+pub fn request(method: &str, url: &str, body: Option<&str>) {
+    tracing::debug!("> {} {}", method, redact_ibans(url));
+    if let Some(body) = body {
+        tracing::debug!("> {}", redact(body));
+    }
+    if !is_enabled() {
+        return;
+    }
+    let mut text = format!("--- {}\n", chrono::Utc::now().to_rfc3339());
+    text.push_str(&format!("> {} {}\n", method, redact_ibans(url)));
+    text.push_str(&format!("> authorization: Bearer {}\n", REDACTED));
+    if let Some(body) = body {
+        text.push_str(&prefixed(">", &redact(body)));
+    }
+    write(&text);
+}
+
+/// A header's value as fit to record, with cookies and credentials
+/// redacted.
+fn header_value<'a>(
+    name: &reqwest::header::HeaderName,
+    value: &'a reqwest::header::HeaderValue,
+) -> &'a str {
+    if SECRET_HEADERS.contains(&name.as_str()) {
+        return REDACTED;
+    }
+    value.to_str().unwrap_or("<binary>")
+}
+
+pub fn response(status: u16, headers: &reqwest::header::HeaderMap, body: &str) {
+    tracing::debug!("< {}", status);
+    for (name, value) in headers {
+        tracing::trace!("< {}: {}", name, header_value(name, value));
+    }
+    tracing::debug!("< {}", redact(body));
+    if !is_enabled() {
+        return;
+    }
+    let mut text = format!("< {}\n", status);
+    for (name, value) in headers {
+        text.push_str(&format!("< {}: {}\n", name, header_value(name, value)));
+    }
+    text.push_str(&prefixed("<", &redact(body)));
+    write(&text);
+}
+
+pub fn failure(error: &str) {
+    tracing::warn!("! {}", redact_ibans(error));
+    if !is_enabled() {
+        return;
+    }
+    write(&format!("! {}\n", redact_ibans(error)));
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::{create_private, header_value, looks_like_iban, redact};
+    use super::{redact_ibans, REDACTED};
+
+    fn redacted(body: &str) -> serde_json::Value {
+        serde_json::from_str(&redact(body)).unwrap()
+    }
+
+    #[test]
+    fn redacts_secret_keys_in_either_casing() {
+        let value = redacted(
+            r#"{"secret_id": "a", "secretId": "b", "SECRET_KEY": "c",
+                "access": "d", "refresh": "e", "status": "LN"}"#,
+        );
+        for key in ["secret_id", "secretId", "SECRET_KEY", "access", "refresh"]
+        {
+            assert_eq!(value[key], REDACTED, "{}", key);
+        }
+        assert_eq!(value["status"], "LN");
+    }
+
+    #[test]
+    fn redacts_nested_account_identifiers() {
+        let value = redacted(
+            r#"{"transactions": {"booked": [{
+                "debtorName": "Jane Doe",
+                "debtorAccount": {"iban": "PT50000201231234567890154"},
+                "transactionAmount": {"amount": "-1.00", "currency": "EUR"}
+            }]}}"#,
+        );
+        let tx = &value["transactions"]["booked"][0];
+        assert_eq!(tx["debtorName"], REDACTED);
+        assert_eq!(tx["debtorAccount"]["iban"], REDACTED);
+        assert_eq!(tx["transactionAmount"]["amount"], "-1.00");
+    }
+
+    #[test]
+    fn redacts_remittance_information() {
+        let value = redacted(
+            r#"{"remittanceInformationUnstructured": "Rent, J. Doe 1234567",
+                "remittanceInformationUnstructuredArray": ["J. Doe"]}"#,
+        );
+        assert_eq!(value["remittanceInformationUnstructured"], REDACTED);
+        assert_eq!(value["remittanceInformationUnstructuredArray"], REDACTED);
+    }
+
+    #[test]
+    fn redacts_ibans_in_free_text_and_urls() {
+        let value = redacted(
+            r#"{"additionalInformation": "To DE89370400440532013000, ta"}"#,
+        );
+        assert_eq!(
+            value["additionalInformation"],
+            format!("To {}, ta", REDACTED)
+        );
+        assert_eq!(
+            redact_ibans(
+                "https://host/accounts/?iban=DE89370400440532013000&x=1"
+            ),
+            format!("https://host/accounts/?iban={}&x=1", REDACTED)
+        );
+    }
+
+    #[test]
+    fn redacts_non_json_bodies_as_text() {
+        assert_eq!(
+            redact("Bad Gateway for GB82WEST12345698765432"),
+            format!("Bad Gateway for {}", REDACTED)
+        );
+        assert_eq!(redact(""), "");
+    }
+
+    #[test]
+    fn ibans_are_15_to_34_characters() {
+        assert!(looks_like_iban("NO9386011117947"));
+        assert!(!looks_like_iban("NO938601111794"));
+        let longest = format!("GB00{}", "A1".repeat(15));
+        assert_eq!(longest.len(), 34);
+        assert!(looks_like_iban(&longest));
+        assert!(!looks_like_iban(&format!("{}2", longest)));
+        assert!(!looks_like_iban("gb82west12345698765432"));
+        assert!(!looks_like_iban("GBXXWEST12345698765432"));
+    }
+
+    #[test]
+    fn redacts_cookies_and_credentials_in_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("set-cookie", HeaderValue::from_static("session=abc"));
+        headers.insert("Cookie", HeaderValue::from_static("session=abc"));
+        headers.insert("content-type", HeaderValue::from_static("text/html"));
+        for (name, value) in &headers {
+            let expected = match name.as_str() {
+                "content-type" => "text/html",
+                _ => REDACTED,
+            };
+            assert_eq!(header_value(name, value), expected, "{}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn trace_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir()
+            .join(format!("nordigen-cli-trace-{}", std::process::id()));
+        std::fs::write(&path, "old").unwrap();
+        let perms = std::fs::Permissions::from_mode(0o644);
+        std::fs::set_permissions(&path, perms).unwrap();
+
+        drop(create_private(&path).unwrap());
+        let meta = std::fs::metadata(&path).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(meta.len(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,44 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use crate::cli::TRANSACTION_FORMATS;
+use crate::schema;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Set by build.rs; empty when not built from a git checkout.
+const COMMIT: &str = env!("NORDIGEN_CLI_COMMIT");
+const FEATURES: &str = env!("NORDIGEN_CLI_FEATURES");
+
+fn features() -> Vec<&'static str> {
+    FEATURES.split(',').filter(|f| !f.is_empty()).collect()
+}
+
+/// Print the version, or with `json`, what wrapper tools need to know of
+/// this build: version, commit, cargo features and output formats.
+pub fn print(json: bool) {
+    if !json {
+        match COMMIT.is_empty() {
+            true => println!("nordigen-cli {}", VERSION),
+            false => println!("nordigen-cli {} ({})", VERSION, COMMIT),
+        }
+        return;
+    }
+
+    let info = serde_json::json!({
+        "name": "nordigen-cli",
+        "version": VERSION,
+        "commit": if COMMIT.is_empty() { None } else { Some(COMMIT) },
+        "features": features(),
+        "formats": {
+            "transactions": TRANSACTION_FORMATS,
+            "progress": ["json"],
+            "schemas": schema::MODELS,
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&info).unwrap());
+}
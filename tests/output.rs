@@ -0,0 +1,177 @@
+// nordigen-cli: A simple Nordigen client
+// Copyright (C) 2022  Joao Eduardo Luis <joao@abysmo.io>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use nordigen::state::NordigenState;
+
+/// An empty directory of the test's own, standing in for HOME and the
+/// config directory so that no config or state of the machine running the
+/// tests is picked up; `run` keeps its keyring out of the way too.
+fn sandbox(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "nordigen-cli-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("failed to create test directory");
+    dir
+}
+
+fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).display().to_string()
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nordigen-cli"))
+        .arg("--config-dir")
+        .arg(dir)
+        .args(args)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("NO_PROXY", "127.0.0.1")
+        .env("NORDIGEN_NO_KEYRING", "1")
+        .env_remove("NORDIGEN_SECRET_ID")
+        .env_remove("NORDIGEN_SECRET_KEY")
+        .env_remove("NORDIGEN_PASSPHRASE")
+        .output()
+        .expect("failed to run nordigen-cli")
+}
+
+/// Answer every request with `body`, as JSON, standing in for the
+/// Nordigen API; returns the URL to pass as `--base-url`.
+fn serve(body: &'static str) -> String {
+    let listener =
+        TcpListener::bind("127.0.0.1:0").expect("failed to bind mock API");
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Err(_) => continue,
+                Ok(res) => res,
+            };
+            // requests are GETs, so the headers are all there is to read
+            let mut reader = BufReader::new(&stream);
+            loop {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) if line == "\r\n" => break,
+                    Ok(_) => {}
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: application/json\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// A state file with tokens that won't need refreshing.
+fn write_state(dir: &Path) {
+    let state = NordigenState::new(
+        String::from("access-token"),
+        3600,
+        String::from("refresh-token"),
+        86400,
+    );
+    let contents = serde_json::to_string_pretty(&state).unwrap();
+    std::fs::write(dir.join("state.json"), contents)
+        .expect("failed to write state");
+}
+
+fn assert_diagnostics_only(out: &Output) {
+    assert!(!out.status.success());
+    assert!(
+        out.stdout.is_empty(),
+        "unexpected stdout: {}",
+        String::from_utf8_lossy(&out.stdout)
+    );
+    assert!(!out.stderr.is_empty());
+}
+
+#[test]
+fn authorize_progress_goes_to_stderr() {
+    let dir = sandbox("authorize");
+    let out = run(&dir, &["authorize"]);
+    assert_diagnostics_only(&out);
+}
+
+#[test]
+fn refresh_errors_go_to_stderr() {
+    let dir = sandbox("refresh");
+    let out = run(&dir, &["refresh"]);
+    assert_diagnostics_only(&out);
+}
+
+#[test]
+fn bank_errors_go_to_stderr() {
+    let dir = sandbox("bank-errors");
+    let auth = path(&dir, "bank.json");
+
+    let out = run(&dir, &["bank", "list"]);
+    assert_diagnostics_only(&out);
+
+    let out = run(
+        &dir,
+        &[
+            "bank",
+            "account",
+            "--auth",
+            &auth,
+            "balance",
+            "--iban",
+            "PT50000000000000000000000",
+        ],
+    );
+    assert_diagnostics_only(&out);
+}
+
+#[test]
+fn bank_list_prints_only_the_table() {
+    let dir = sandbox("bank-list");
+    write_state(&dir);
+    let url = serve(
+        r#"[{
+            "id": "MOCKBANK_MCKBPTPL",
+            "name": "Mock Bank",
+            "bic": "MCKBPTPL",
+            "transaction_total_days": "730",
+            "countries": ["PT"],
+            "logo": null
+        }]"#,
+    );
+
+    let out = run(&dir, &["--base-url", &url, "bank", "list"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("MOCKBANK_MCKBPTPL"), "stdout: {}", stdout);
+    assert!(
+        stdout
+            .lines()
+            .all(|line| line.starts_with('+') || line.starts_with('|')),
+        "unexpected stdout: {}",
+        stdout
+    );
+}